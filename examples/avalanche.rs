@@ -0,0 +1,91 @@
+// Diffusion of the default pipeline against its cascaded and fractionated
+// variants over the built-in corpus.
+//
+//     cargo run --release --example avalanche [samples.csv]
+
+use playfair_dna::cryptanalysis::avalanche;
+use playfair_dna::pipeline::{Config, Stage};
+use std::fs::File;
+
+const KEYWORD: &str = "PLAYFAIRDNA";
+const BIT_STEP: usize = 3;
+
+fn main() -> std::io::Result<()> {
+    let modes = [
+        ("playfair", Config::default()),
+        (
+            "playfair-3-rounds",
+            Config {
+                rounds: 3,
+                ..Default::default()
+            },
+        ),
+        (
+            "bifid",
+            Config {
+                stage: Stage::Bifid(5),
+                ..Default::default()
+            },
+        ),
+        (
+            "playfair-bifid",
+            Config {
+                stage: Stage::PlayfairBifid(5),
+                ..Default::default()
+            },
+        ),
+        (
+            "trifid",
+            Config {
+                stage: Stage::Trifid(5),
+                ..Default::default()
+            },
+        ),
+        (
+            "adfgx",
+            Config {
+                stage: Stage::Adfgx("CARGO".to_string()),
+                ..Default::default()
+            },
+        ),
+        (
+            "masked-ambig",
+            Config {
+                mask_ambig: true,
+                ..Default::default()
+            },
+        ),
+    ];
+
+    let messages = avalanche::corpus();
+    let mut samples = Vec::new();
+    for (mode, config) in modes.iter() {
+        samples.extend(avalanche::measure(
+            mode, config, KEYWORD, &messages, BIT_STEP,
+        ));
+    }
+
+    println!(
+        "{:<20} {:<14} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7}",
+        "mode", "flip", "samples", "mean", "std", "min", "max", "ambig"
+    );
+    for s in avalanche::summarize(&samples) {
+        println!(
+            "{:<20} {:<14} {:>7} {:>7.3} {:>7.3} {:>7.3} {:>7.3} {:>7.3}",
+            s.mode,
+            s.flip.string(),
+            s.samples,
+            s.mean,
+            s.std_dev,
+            s.min,
+            s.max,
+            s.ambig_mean
+        );
+    }
+
+    if let Some(path) = std::env::args().nth(1) {
+        avalanche::write_csv(&samples, File::create(&path)?)?;
+        println!("samples written to {}", path);
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use crate::errors::Failure;
+use crate::options::{IoArgs, PipelineArgs};
+use clap::Args;
+use playfair_dna::cryptanalysis::dictionary::{self, DictionaryParams};
+use playfair_dna::cryptanalysis::known_plaintext::{self, Reconstruction};
+use playfair_dna::pipeline::defaults::{self, Defaults, Layout};
+use playfair_dna::playfair;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+
+#[derive(Args, Debug)]
+pub struct SolveArgs {
+    /// File with the plaintext of the cipher read from the input
+    #[arg(long, value_name = "PATH")]
+    plaintext: PathBuf,
+    #[command(flatten)]
+    io: IoArgs,
+    /// Where the ambiguity section goes [default: before]
+    #[arg(long, value_enum)]
+    layout: Option<Layout>,
+    /// Most candidate matrices to list
+    #[arg(long, default_value_t = 10)]
+    limit: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct CrackArgs {
+    /// One candidate keyword per line
+    #[arg(long, value_name = "PATH")]
+    wordlist: PathBuf,
+    #[command(flatten)]
+    io: IoArgs,
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+    /// Also try lower case, upper case and capitalized spellings
+    #[arg(long)]
+    case_variants: bool,
+    /// Also try look-alike digit substitutions
+    #[arg(long)]
+    leetspeak: bool,
+    /// Stop at the first guess scoring at least this much
+    #[arg(long)]
+    stop_score: Option<f64>,
+    /// Number of best guesses to print
+    #[arg(long, default_value_t = 10)]
+    keep: usize,
+}
+
+pub fn solve(args: &SolveArgs, defaults: &Defaults) -> Result<(), Box<dyn Error>> {
+    let plaintext = fs::read_to_string(&args.plaintext)
+        .map_err(|e| Failure::io(format!("cannot read {}", args.plaintext.display()), e))?;
+    let cipher = defaults::strip_fasta(&args.io.read()?);
+    let before = match args.layout {
+        Some(layout) => layout == Layout::Before,
+        None => defaults.layout != Some(Layout::After),
+    };
+    let (dna, ambig) = playfair::split_cipher(&cipher, before);
+    let reconstruction = known_plaintext::reconstruct(&plaintext, &dna, &ambig, args.limit)?;
+
+    let mut out = format!(
+        "{} digraphs, {} candidate matrices{}\n",
+        reconstruction.digraphs,
+        reconstruction.candidates.len(),
+        match reconstruction.complete {
+            true => "",
+            false => " (search stopped early)",
+        }
+    );
+    for candidate in reconstruction.candidates.iter() {
+        out += &format!("\n{}\n", Reconstruction::format(candidate));
+    }
+    args.io.write(&out)
+}
+
+pub fn crack(args: &CrackArgs, defaults: &Defaults) -> Result<(), Box<dyn Error>> {
+    let words = dictionary::load_wordlist(&args.wordlist)
+        .map_err(|e| Failure::io(format!("cannot read {}", args.wordlist.display()), e))?;
+    let cipher = defaults::strip_fasta(&args.io.read()?);
+    let params = DictionaryParams {
+        case_variants: args.case_variants,
+        leetspeak: args.leetspeak,
+        stop_score: args.stop_score,
+        keep: args.keep,
+        ..Default::default()
+    };
+    let stop = AtomicBool::new(false);
+    let guesses = dictionary::crack(
+        &cipher,
+        &args.pipeline.config(defaults)?,
+        &words,
+        &params,
+        &stop,
+        |progress| {
+            eprintln!(
+                "{}/{} tried, best: {}",
+                progress.tried,
+                progress.total,
+                progress.best.map_or("-", |best| best.word.as_str())
+            )
+        },
+    );
+
+    let mut out = String::new();
+    for guess in guesses.iter() {
+        out += &format!(
+            "{:>10.2}  {}  {}\n    {:?}\n",
+            guess.score,
+            guess.word,
+            guess.status.string(),
+            guess.text
+        );
+    }
+    args.io.write(&out)
+}
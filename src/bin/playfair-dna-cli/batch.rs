@@ -0,0 +1,331 @@
+use crate::errors::{Failure, Kind};
+use crate::options::{self, EncryptOptions, KeyArgs, OutputArgs, PipelineArgs, Settings};
+use clap::{Args, Subcommand};
+use playfair_dna::pipeline::defaults::{self, Defaults, Format, StageName};
+use playfair_dna::pipeline::{self, Config, Status};
+use playfair_dna::playfair::{self, keygen};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// Whole directory trees. Every regular file is encrypted on its own, with the
+// master key or a key derived from it and the file's relative path, and the
+// manifest records what the decrypt side needs to restore and check the tree.
+
+const MANIFEST: &str = "manifest.json";
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    #[command(subcommand)]
+    command: BatchCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum BatchCommand {
+    /// Encrypt every UTF-8 text file of a directory tree and write a
+    /// manifest; other files are skipped and left out of the manifest
+    Encrypt(BatchEncryptArgs),
+    /// Restore a tree from its manifest and verify the hashes
+    Decrypt(BatchDecryptArgs),
+}
+
+#[derive(Args, Debug)]
+struct BatchEncryptArgs {
+    /// Directory to encrypt
+    input_dir: PathBuf,
+    /// Directory for the ciphers and the manifest
+    output_dir: PathBuf,
+    #[command(flatten)]
+    key: KeyArgs,
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+    #[command(flatten)]
+    options: EncryptOptions,
+    /// Derive a separate key for every file from the key and its path
+    #[arg(long)]
+    per_file_keys: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args, Debug)]
+struct BatchDecryptArgs {
+    /// Directory written by `batch encrypt`
+    input_dir: PathBuf,
+    /// Directory to restore the files into
+    output_dir: PathBuf,
+    #[command(flatten)]
+    key: KeyArgs,
+    /// Transposition keyword, for trees encrypted with the ADFGX stage; the
+    /// manifest does not record it
+    #[arg(long, value_name = "KEYWORD", value_parser = options::transposition)]
+    transposition: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Relative to the input directory, `/` separated.
+    path: String,
+    size: u64,
+    sha256: String,
+    /// Relative to the output directory.
+    cipher: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: Format,
+    per_file_keys: bool,
+    settings: Settings,
+    files: Vec<Entry>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Key matrix of a single file, bound to its relative path.
+fn file_key(master: &[char], path: &str) -> Vec<char> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"playfair-dna file key");
+    hasher.update(master.iter().collect::<String>().as_bytes());
+    hasher.update(path.as_bytes());
+    keygen::key_from_bytes(&hasher.finalize())
+}
+
+/// Regular files below `dir` as `/` separated relative paths, sorted,
+/// skipping `exclude` (the output directory when it lies inside).
+fn walk(dir: &Path, exclude: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    fn visit(
+        root: &Path,
+        dir: &Path,
+        exclude: Option<&Path>,
+        files: &mut Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let kind = fs::symlink_metadata(&path)?.file_type();
+            if kind.is_dir() && fs::canonicalize(&path).ok().as_deref() == exclude {
+                continue;
+            }
+            if kind.is_dir() {
+                visit(root, &path, exclude, files)?;
+            } else if kind.is_file() {
+                let relative = path.strip_prefix(root)?;
+                let parts: Vec<String> = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                files.push(parts.join("/"));
+            }
+        }
+        Ok(())
+    }
+    let mut files: Vec<String> = Vec::new();
+    let exclude = fs::canonicalize(exclude).ok();
+    visit(dir, dir, exclude.as_deref(), &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// A manifest path under `dir`, refusing anything that would leave it.
+fn join(dir: &Path, relative: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let relative = Path::new(relative);
+    if !relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(format!("unsafe path in manifest: {}", relative.display()).into());
+    }
+    Ok(dir.join(relative))
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)
+        .map_err(|e| Failure::io(format!("cannot write {}", path.display()), e).into())
+}
+
+/// One error of the kind of the first failure, so the exit code and the
+/// `--json` report say why the run failed.
+fn summary(failures: Vec<Failure>, done: &str) -> Result<(), Box<dyn Error>> {
+    let count = failures.len();
+    match failures.into_iter().next() {
+        None => Ok(()),
+        Some(first) => Err(Failure {
+            message: format!(
+                "{} files could not be {}, first {}",
+                count, done, first.message
+            ),
+            ..first
+        }
+        .into()),
+    }
+}
+
+fn encrypt_file(
+    args: &BatchEncryptArgs,
+    defaults: &Defaults,
+    key: &str,
+    path: &str,
+) -> Result<Option<Entry>, Box<dyn Error>> {
+    let source = args.input_dir.join(path);
+    let bytes = fs::read(&source).map_err(|e| Failure::io(format!("cannot read {}", path), e))?;
+    let Ok(text) = String::from_utf8(bytes.clone()) else {
+        return Ok(None);
+    };
+    let config = args
+        .options
+        .apply(args.pipeline.config(defaults)?, defaults);
+    let mut matrix = options::resolve(key, &config.header())?;
+    if args.per_file_keys {
+        matrix = file_key(&matrix, path);
+    }
+    let cipher = pipeline::encrypt(&text, &matrix, &config).cipher(&config);
+
+    let output = args.output.output(defaults);
+    let cipher_path = format!("{}.{}", path, output.format.extension());
+    let contents = output.render(&cipher, path);
+    write_file(&args.output_dir.join(&cipher_path), contents.as_bytes())?;
+    Ok(Some(Entry {
+        path: path.to_string(),
+        size: bytes.len() as u64,
+        sha256: hex(&Sha256::digest(&bytes)),
+        cipher: cipher_path,
+    }))
+}
+
+fn encrypt(args: &BatchEncryptArgs, defaults: &Defaults) -> Result<(), Box<dyn Error>> {
+    let key = args.key.read()?;
+    let files = walk(&args.input_dir, &args.output_dir)?;
+    let results: Vec<Result<Option<Entry>, Failure>> = files
+        .par_iter()
+        .map(|path| encrypt_file(args, defaults, &key, path).map_err(Failure::from_error))
+        .collect();
+
+    let mut entries: Vec<Entry> = Vec::with_capacity(results.len());
+    let mut skipped = 0;
+    let mut failures: Vec<Failure> = Vec::new();
+    for (path, result) in files.iter().zip(results) {
+        match result {
+            Ok(Some(entry)) => entries.push(entry),
+            Ok(None) => {
+                eprintln!("{}: not UTF-8 text, skipped", path);
+                skipped += 1;
+            }
+            Err(failure) => {
+                eprintln!("{}", failure);
+                failures.push(failure);
+            }
+        }
+    }
+    let manifest = Manifest {
+        format: args.output.output(defaults).format,
+        per_file_keys: args.per_file_keys,
+        settings: Settings::new(
+            &args
+                .options
+                .apply(args.pipeline.config(defaults)?, defaults),
+        ),
+        files: entries,
+    };
+    write_file(
+        &args.output_dir.join(MANIFEST),
+        (serde_json::to_string_pretty(&manifest)? + "\n").as_bytes(),
+    )?;
+    eprintln!(
+        "{} files encrypted, {} skipped, {} failed",
+        manifest.files.len(),
+        skipped,
+        failures.len()
+    );
+    summary(failures, "encrypted")
+}
+
+fn decrypt_file(
+    args: &BatchDecryptArgs,
+    manifest: &Manifest,
+    config: &Config,
+    key: &str,
+    entry: &Entry,
+) -> Result<(), Box<dyn Error>> {
+    let source = join(&args.input_dir, &entry.cipher)?;
+    let contents = fs::read_to_string(&source)
+        .map_err(|e| Failure::io(format!("cannot read {}", source.display()), e))?;
+    let cipher = defaults::strip_fasta(&contents);
+    let mut matrix = options::resolve(key, &playfair::cipher_header(&cipher).unwrap_or_default())?;
+    if manifest.per_file_keys {
+        matrix = file_key(&matrix, &entry.path);
+    }
+    let de = pipeline::decrypt(&cipher, &matrix, config);
+    if !matches!(
+        de.status,
+        Status::Empty | Status::Decrypted | Status::Verified
+    ) {
+        return Err(Failure::decrypt(&de.status, de.diagnostics)
+            .about(&entry.path)
+            .into());
+    }
+    // Ciphers from before the self-describing padding end in spaces, the
+    // manifest knows the real size
+    let mut bytes = de.plain_text.into_bytes();
+    bytes.truncate(entry.size as usize);
+    if hex(&Sha256::digest(&bytes)) != entry.sha256 {
+        return Err(Failure::new(
+            Kind::Authentication,
+            format!("{}: hash mismatch", entry.path),
+        )
+        .into());
+    }
+    write_file(&join(&args.output_dir, &entry.path)?, &bytes)
+}
+
+fn decrypt(args: &BatchDecryptArgs) -> Result<(), Box<dyn Error>> {
+    let path = args.input_dir.join(MANIFEST);
+    let manifest: Manifest = serde_json::from_str(
+        &fs::read_to_string(&path)
+            .map_err(|e| Failure::io(format!("cannot read {}", path.display()), e))?,
+    )
+    .map_err(|e| format!("invalid manifest {}: {}", path.display(), e))?;
+    if manifest.settings.stage == StageName::Adfgx && args.transposition.is_none() {
+        return Err(Failure::new(
+            Kind::BadKey,
+            "the tree was encrypted with the ADFGX stage, pass its keyword with --transposition",
+        )
+        .into());
+    }
+    let config = manifest
+        .settings
+        .config(args.transposition.as_deref())
+        .map_err(|e| format!("invalid manifest {}: {}", path.display(), e))?;
+    let key = args.key.read()?;
+    let failures: Vec<Failure> = manifest
+        .files
+        .par_iter()
+        .filter_map(|entry| {
+            decrypt_file(args, &manifest, &config, &key, entry)
+                .err()
+                .map(Failure::from_error)
+        })
+        .collect();
+    for failure in failures.iter() {
+        eprintln!("{}", failure);
+    }
+    eprintln!(
+        "{} files restored and verified, {} failed",
+        manifest.files.len() - failures.len(),
+        failures.len()
+    );
+    summary(failures, "restored")
+}
+
+pub fn batch(args: &BatchArgs, defaults: &Defaults) -> Result<(), Box<dyn Error>> {
+    match &args.command {
+        BatchCommand::Encrypt(args) => encrypt(args, defaults),
+        BatchCommand::Decrypt(args) => decrypt(args),
+    }
+}
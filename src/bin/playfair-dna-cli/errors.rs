@@ -0,0 +1,200 @@
+use playfair_dna::pipeline::defaults::DefaultsError;
+use playfair_dna::pipeline::diagnostics::{Cause, Diagnostic};
+use playfair_dna::pipeline::Status;
+use playfair_dna::playfair::keygen::KeyStringError;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// Failures a script can tell apart by the exit code, or with --json by the
+// report on stderr. Clap exits with 2 on usage errors before any of this.
+
+pub const EXIT_CODES: &str = "Exit codes:
+  0  success
+  1  other failure
+  2  invalid command line
+  3  I/O error: a file or stream could not be read or written
+  4  bad key: missing, not a valid key string, or key derivation failed
+  5  malformed cipher: not sections of nucleotides that decrypt
+  6  layout mismatch: the ambiguity section is on the other side
+  7  authentication failed: the tag or a batch file hash does not match
+  8  invalid text: the decrypted bytes are not text in the chosen encoding
+  9  config file: unreadable or invalid
+A batch run that fails on some files exits with the code of the first.";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Other,
+    Io,
+    BadKey,
+    MalformedCipher,
+    LayoutMismatch,
+    Authentication,
+    InvalidText,
+    Config,
+}
+
+impl Kind {
+    pub fn code(&self) -> u8 {
+        match self {
+            Kind::Other => 1,
+            Kind::Io => 3,
+            Kind::BadKey => 4,
+            Kind::MalformedCipher => 5,
+            Kind::LayoutMismatch => 6,
+            Kind::Authentication => 7,
+            Kind::InvalidText => 8,
+            Kind::Config => 9,
+        }
+    }
+
+    pub fn string(&self) -> &'static str {
+        match self {
+            Kind::Other => "other",
+            Kind::Io => "io",
+            Kind::BadKey => "bad-key",
+            Kind::MalformedCipher => "malformed-cipher",
+            Kind::LayoutMismatch => "layout-mismatch",
+            Kind::Authentication => "authentication",
+            Kind::InvalidText => "invalid-text",
+            Kind::Config => "config",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Failure {
+    pub kind: Kind,
+    pub message: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Failure {
+    pub fn new(kind: Kind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// An I/O error with what was being done, e.g. "cannot read x.txt".
+    pub fn io(context: String, e: io::Error) -> Self {
+        Self::new(Kind::Io, format!("{}: {}", context, e))
+    }
+
+    /// A decryption that did not give text.
+    pub fn decrypt(status: &Status, diagnostics: Vec<Diagnostic>) -> Self {
+        // The diagnostic that stopped the decryption comes last
+        let layout = diagnostics
+            .last()
+            .is_some_and(|d| d.causes.first() == Some(&Cause::WrongLayout));
+        let kind = match status {
+            Status::BadTag => Kind::Authentication,
+            Status::InvalidText => Kind::InvalidText,
+            Status::Malformed(_) if layout => Kind::LayoutMismatch,
+            _ => Kind::MalformedCipher,
+        };
+        Self {
+            kind,
+            message: status.string(),
+            diagnostics,
+        }
+    }
+}
+
+impl Failure {
+    /// Any command error as a `Failure` of its kind, e.g. to send it to
+    /// another thread.
+    pub fn from_error(e: Box<dyn Error>) -> Self {
+        match e.downcast::<Failure>() {
+            Ok(failure) => *failure,
+            Err(e) => Self::new(kind(e.as_ref()), e.to_string()),
+        }
+    }
+
+    /// The same failure with what it is about in front of the message.
+    pub fn about(mut self, subject: &str) -> Self {
+        self.message = format!("{}: {}", subject, self.message);
+        self
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for Failure {}
+
+/// The kind of any error a command returns.
+pub fn kind(e: &(dyn Error + 'static)) -> Kind {
+    if let Some(failure) = e.downcast_ref::<Failure>() {
+        failure.kind
+    } else if e.is::<io::Error>() {
+        Kind::Io
+    } else if e.is::<DefaultsError>() {
+        Kind::Config
+    } else if e.is::<KeyStringError>() {
+        Kind::BadKey
+    } else {
+        Kind::Other
+    }
+}
+
+#[derive(Serialize)]
+pub struct DiagnosticReport {
+    pub stage: String,
+    pub message: String,
+    pub offsets: Vec<[usize; 2]>,
+    pub causes: Vec<String>,
+}
+
+impl DiagnosticReport {
+    pub fn new(d: &Diagnostic) -> Self {
+        Self {
+            stage: d.stage.string().to_string(),
+            message: d.message.clone(),
+            offsets: d.offsets.iter().map(|r| [r.start, r.end]).collect(),
+            causes: d.causes.iter().map(|c| c.string().to_string()).collect(),
+        }
+    }
+}
+
+/// What `--json` prints to stderr. `stage` and `offsets` are those of the
+/// diagnostic that stopped the decryption, if there is one.
+#[derive(Serialize)]
+struct Report {
+    error: &'static str,
+    exit_code: u8,
+    message: String,
+    stage: Option<String>,
+    offsets: Vec<[usize; 2]>,
+    diagnostics: Vec<DiagnosticReport>,
+}
+
+pub fn json(e: &(dyn Error + 'static)) -> String {
+    let kind = kind(e);
+    let diagnostics: Vec<DiagnosticReport> = e
+        .downcast_ref::<Failure>()
+        .map(|failure| {
+            failure
+                .diagnostics
+                .iter()
+                .map(DiagnosticReport::new)
+                .collect()
+        })
+        .unwrap_or_default();
+    let last = diagnostics.last();
+    let report = Report {
+        error: kind.string(),
+        exit_code: kind.code(),
+        message: e.to_string(),
+        stage: last.map(|d| d.stage.clone()),
+        offsets: last.map(|d| d.offsets.clone()).unwrap_or_default(),
+        diagnostics,
+    };
+    serde_json::to_string(&report).unwrap_or_default()
+}
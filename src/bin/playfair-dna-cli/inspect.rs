@@ -0,0 +1,187 @@
+use crate::errors::DiagnosticReport;
+use crate::options::{EncryptOptions, IoArgs, KeyArgs, PipelineArgs, Settings};
+use clap::{Args, ValueEnum};
+use playfair_dna::pipeline::defaults::{self, Defaults};
+use playfair_dna::pipeline::{self, Config};
+use playfair_dna::playfair::{self, nonce};
+use serde::Serialize;
+use std::error::Error;
+
+// Every intermediate stage of one run, as the GUI shows them under "Extra",
+// for diffing runs and attaching to bug reports.
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Format {
+    Json,
+    Table,
+}
+
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    #[command(flatten)]
+    key: KeyArgs,
+    #[command(flatten)]
+    io: IoArgs,
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+    /// Only when encrypting; a cipher carries its own in the header
+    #[command(flatten)]
+    options: EncryptOptions,
+    /// Treat the input as a cipher and inspect its decryption
+    #[arg(long)]
+    decrypt: bool,
+    #[arg(long, value_enum, default_value = "json")]
+    format: Format,
+}
+
+#[derive(Serialize)]
+struct Inspection {
+    mode: &'static str,
+    settings: Settings,
+    key_matrix: Vec<String>,
+    /// Only with a nonce: the matrix the message is actually encrypted with.
+    message_key_matrix: Option<Vec<String>>,
+    plain_text: String,
+    binary: String,
+    dna: String,
+    acids: String,
+    ambig: String,
+    acids_after_playfair: String,
+    dna_after_playfair: String,
+    /// Indices into the Playfair output (encrypt) or input (decrypt) where a
+    /// filler X was inserted.
+    filler_positions: Vec<usize>,
+    cipher: String,
+    status: Option<String>,
+    diagnostics: Vec<DiagnosticReport>,
+}
+
+fn rows(key: &[char]) -> Vec<String> {
+    key.chunks(5).map(|row| row.iter().collect()).collect()
+}
+
+fn bits(binary: &[u8]) -> String {
+    binary
+        .iter()
+        .map(|byte| format!("{:08b}", byte))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn digits(ambig: &[u8]) -> String {
+    ambig.iter().map(|digit| digit.to_string()).collect()
+}
+
+fn fillers(ambig: &[u8]) -> Vec<usize> {
+    (0..ambig.len()).filter(|&i| ambig[i] == 4).collect()
+}
+
+fn inspect_encrypt(args: &InspectArgs, defaults: &Defaults) -> Result<Inspection, Box<dyn Error>> {
+    let config = args
+        .options
+        .apply(args.pipeline.config(defaults)?, defaults);
+    let key = args.key.matrix(&config.header())?;
+    let text = args.io.read()?;
+    let en = pipeline::encrypt(&text, &key, &config);
+    Ok(Inspection {
+        mode: "encrypt",
+        settings: Settings::new(&config),
+        key_matrix: rows(&key),
+        message_key_matrix: config
+            .nonce
+            .map(|nonce| rows(&nonce::message_key(&key, &nonce))),
+        plain_text: text,
+        binary: bits(&en.binary),
+        filler_positions: fillers(&en.ambig_vec),
+        ambig: digits(&en.ambig_vec),
+        cipher: en.cipher(&config),
+        dna: en.dna,
+        acids: en.acids,
+        acids_after_playfair: en.acids_after_playfair,
+        dna_after_playfair: en.dna_after_playfair,
+        status: None,
+        diagnostics: Vec::new(),
+    })
+}
+
+fn inspect_decrypt(args: &InspectArgs, defaults: &Defaults) -> Result<Inspection, Box<dyn Error>> {
+    let cipher = defaults::strip_fasta(&args.io.read()?);
+    let header = playfair::cipher_header(&cipher).unwrap_or_default();
+    let config = Config {
+        kdf: header.kdf.clone(),
+        mask_ambig: header.mask_ambig,
+        nonce: header.nonce,
+        mac: header.mac,
+        ..args.pipeline.config(defaults)?
+    };
+    let key = args.key.matrix(&header)?;
+    let de = pipeline::decrypt(&cipher, &key, &config);
+    Ok(Inspection {
+        mode: "decrypt",
+        settings: Settings::new(&config),
+        message_key_matrix: header
+            .nonce
+            .map(|nonce| rows(&nonce::message_key(&key, &nonce))),
+        key_matrix: rows(&key),
+        plain_text: de.plain_text,
+        binary: bits(&de.binary),
+        filler_positions: fillers(&de.ambig_vec),
+        ambig: digits(&de.ambig_vec),
+        dna: de.dna,
+        acids: de.acids,
+        acids_after_playfair: de.acids_after_playfair,
+        dna_after_playfair: de.dna_after_playfair,
+        cipher: cipher.trim_end().to_string(),
+        status: Some(de.status.string()),
+        diagnostics: de.diagnostics.iter().map(DiagnosticReport::new).collect(),
+    })
+}
+
+fn table(inspection: &Inspection) -> String {
+    let mut rows: Vec<(&str, String)> = vec![
+        ("mode", inspection.mode.to_string()),
+        (
+            "settings",
+            serde_json::to_string(&inspection.settings).unwrap_or_default(),
+        ),
+        ("key matrix", inspection.key_matrix.join(" ")),
+    ];
+    if let Some(message_key) = &inspection.message_key_matrix {
+        rows.push(("message key", message_key.join(" ")));
+    }
+    rows.extend([
+        ("plain text", format!("{:?}", inspection.plain_text)),
+        ("binary", inspection.binary.clone()),
+        ("dna", inspection.dna.clone()),
+        ("acids", inspection.acids.clone()),
+        ("ambig", inspection.ambig.clone()),
+        ("acids after", inspection.acids_after_playfair.clone()),
+        ("dna after", inspection.dna_after_playfair.clone()),
+        ("fillers", format!("{:?}", inspection.filler_positions)),
+        ("cipher", inspection.cipher.clone()),
+    ]);
+    if let Some(status) = &inspection.status {
+        rows.push(("status", status.clone()));
+    }
+    for d in inspection.diagnostics.iter() {
+        rows.push((
+            "diagnostic",
+            format!("{}: {} ({})", d.stage, d.message, d.causes.join(", ")),
+        ));
+    }
+    rows.iter()
+        .map(|(label, value)| format!("{:<12} {}\n", label, value))
+        .collect()
+}
+
+pub fn inspect(args: &InspectArgs, defaults: &Defaults) -> Result<(), Box<dyn Error>> {
+    let inspection = match args.decrypt {
+        true => inspect_decrypt(args, defaults)?,
+        false => inspect_encrypt(args, defaults)?,
+    };
+    let out = match args.format {
+        Format::Json => serde_json::to_string_pretty(&inspection)? + "\n",
+        Format::Table => table(&inspection),
+    };
+    args.io.write(&out)
+}
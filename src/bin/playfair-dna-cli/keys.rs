@@ -0,0 +1,175 @@
+use crate::errors::{Failure, Kind};
+use crate::options::KeyArgs;
+use clap::Args;
+use playfair_dna::playfair::{self, header::Header, kdf, kdf::KdfParams, keygen, strength};
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct KeygenArgs {
+    /// Derive the key from the passphrase given with --key, --key-file or
+    /// --key-env instead of drawing a random one
+    #[arg(long)]
+    passphrase: bool,
+    /// Argon2 salt as 32 hex digits, random if omitted
+    #[arg(long, requires = "passphrase")]
+    salt: Option<String>,
+    #[command(flatten)]
+    key: KeyArgs,
+    /// Key file to write, stdout if omitted or "-"
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct MatrixArgs {
+    #[command(flatten)]
+    key: KeyArgs,
+    /// Mark the cells a plaintext digraph and its encryption use, e.g. HE
+    #[arg(long, value_name = "DIGRAPH")]
+    highlight: Option<String>,
+}
+
+fn parse_salt(hex: &str) -> Result<[u8; kdf::SALT_LEN], Box<dyn Error>> {
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or("the salt must be hex digits")?;
+    if digits.len() != kdf::SALT_LEN * 2 {
+        return Err(format!("the salt must be {} hex digits", kdf::SALT_LEN * 2).into());
+    }
+    let mut salt = [0u8; kdf::SALT_LEN];
+    for (byte, pair) in salt.iter_mut().zip(digits.chunks(2)) {
+        *byte = pair[0] << 4 | pair[1];
+    }
+    Ok(salt)
+}
+
+// Key files are secrets: owner read/write only where the platform has modes.
+// The open mode only applies to new files, so an existing one is narrowed
+// before the key is written into it.
+fn write_key_file(path: &PathBuf, contents: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())
+}
+
+pub fn keygen(args: &KeygenArgs) -> Result<(), Box<dyn Error>> {
+    let matrix = match args.passphrase {
+        true => {
+            let passphrase = args.key.read()?;
+            let params = match &args.salt {
+                Some(salt) => KdfParams {
+                    salt: parse_salt(salt)?,
+                    ..Default::default()
+                },
+                None => KdfParams::random(),
+            };
+            let matrix = kdf::derive_key_matrix(&passphrase, &params)
+                .map_err(|e| Failure::new(Kind::BadKey, format!("key derivation error: {}", e)))?;
+            let salt: String = params.salt.iter().map(|b| format!("{:02x}", b)).collect();
+            eprintln!("salt: {}", salt);
+            let strength = strength::analyze_passphrase(&passphrase, &matrix);
+            eprintln!("strength: {}", strength.rating().string());
+            for warning in strength.warnings() {
+                eprintln!("warning: {}", warning);
+            }
+            matrix
+        }
+        false => keygen::random_key_matrix(),
+    };
+    let key = keygen::export_key(&matrix).ok_or("the key matrix is not a permutation")?;
+    match &args.output {
+        Some(path) if path.as_os_str() != "-" => write_key_file(path, &format!("{}\n", key))
+            .map_err(|e| Failure::io(format!("cannot write {}", path.display()), e).into()),
+        _ => {
+            println!("{}", key);
+            Ok(())
+        }
+    }
+}
+
+/// Rows of the grid as in the GUI key tooltip, with `[P]` around the
+/// plaintext letters and `(C)` around the cipher letters when highlighting.
+fn grid(matrix: &[char], plain: &[char], cipher: &[char], color: bool) -> String {
+    let mut out = String::new();
+    for row in matrix.chunks(5) {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|c| match (plain.contains(c), cipher.contains(c), color) {
+                (true, true, false) => format!("[({})]", c),
+                (true, false, false) => format!("[{}]", c),
+                (false, true, false) => format!("({})", c),
+                (true, _, true) => format!("\x1b[1;32m{}\x1b[0m", c),
+                (false, true, true) => format!("\x1b[1;31m{}\x1b[0m", c),
+                (false, false, _) => c.to_string(),
+            })
+            .collect();
+        let width = match (plain.is_empty(), color) {
+            (false, false) => 5,
+            _ => 1,
+        };
+        out += &cells
+            .iter()
+            .map(|cell| format!("{:^width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join(" ");
+        out.push('\n');
+    }
+    out
+}
+
+fn rule(matrix: &[char], a: char, b: char) -> &'static str {
+    let i = matrix.iter().position(|&c| c == a).unwrap_or(0);
+    let j = matrix.iter().position(|&c| c == b).unwrap_or(0);
+    if i / 5 == j / 5 {
+        "same row"
+    } else if i % 5 == j % 5 {
+        "same column"
+    } else {
+        "rectangle"
+    }
+}
+
+pub fn matrix(args: &MatrixArgs) -> Result<(), Box<dyn Error>> {
+    let matrix = args.key.matrix(&Header::default())?;
+    let color = io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let Some(digraph) = &args.highlight else {
+        print!("{}", grid(&matrix, &[], &[], false));
+        return Ok(());
+    };
+
+    // The grid has no J, which messages write as I
+    let plain: Vec<char> = digraph.to_uppercase().replace('J', "I").chars().collect();
+    if plain.len() != 2 || !plain.iter().all(|c| playfair::ALPHABET.contains(*c)) {
+        return Err(format!(
+            "the digraph must be two letters of {} (J counts as I)",
+            playfair::ALPHABET
+        )
+        .into());
+    }
+    let cipher: Vec<char> = playfair::encrypt_digraphs(&matrix, &plain.iter().collect::<String>())
+        .chars()
+        .collect();
+    print!("{}", grid(&matrix, &plain, &cipher, color));
+    println!(
+        "{}{} -> {}{} ({})",
+        plain[0],
+        plain[1],
+        cipher[0],
+        cipher[1],
+        rule(&matrix, plain[0], plain[1])
+    );
+    if plain[0] == plain[1] {
+        println!("in a message, a filler X splits a doubled letter before this step");
+    }
+    Ok(())
+}
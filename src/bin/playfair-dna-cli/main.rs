@@ -0,0 +1,215 @@
+use clap::{Args, Parser, Subcommand};
+use errors::Failure;
+use options::{EncryptOptions, IoArgs, KeyArgs, OutputArgs, PipelineArgs, RawArgs};
+use playfair_dna::pipeline::defaults::{self, Defaults};
+use playfair_dna::pipeline::{self, selftest, Status};
+use playfair_dna::playfair;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::{panic, thread};
+
+mod attack;
+mod batch;
+mod errors;
+mod inspect;
+mod keys;
+mod options;
+mod tui;
+mod watch;
+
+/// Playfair DNA cipher without the window, for scripts and headless machines.
+#[derive(Parser, Debug)]
+#[command(version, about, after_help = errors::EXIT_CODES)]
+struct Cli {
+    /// Report errors to stderr as a JSON object with the error kind, the
+    /// failing stage and the cipher offsets
+    #[arg(long, global = true)]
+    json: bool,
+    /// Read default options from this file instead of the user and project
+    /// config files
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Encrypt text into a DNA cipher
+    Encrypt(EncryptArgs),
+    /// Decrypt a DNA cipher back into text
+    Decrypt(DecryptArgs),
+    /// Encrypt or restore whole directory trees
+    Batch(batch::BatchArgs),
+    /// Dump every pipeline stage as JSON or a table
+    Inspect(inspect::InspectArgs),
+    /// Create a random or passphrase-derived key string
+    Keygen(keys::KeygenArgs),
+    /// Print the 5x5 key matrix
+    Matrix(keys::MatrixArgs),
+    /// Recover the key matrix from a known plaintext and its cipher
+    Solve(attack::SolveArgs),
+    /// Try every word of a wordlist as the key
+    Crack(attack::CrackArgs),
+    /// Round-trip a built-in corpus to check this build
+    Selftest,
+    /// Encrypt files as they appear in a directory, until SIGTERM
+    Watch(watch::WatchArgs),
+    /// The two encrypt and decrypt panes of the GUI in the terminal
+    Tui(tui::TuiArgs),
+}
+
+#[derive(Args, Debug)]
+struct EncryptArgs {
+    #[command(flatten)]
+    key: KeyArgs,
+    #[command(flatten)]
+    io: IoArgs,
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+    #[command(flatten)]
+    options: EncryptOptions,
+    #[command(flatten)]
+    output: OutputArgs,
+    #[command(flatten)]
+    raw: RawArgs,
+}
+
+#[derive(Args, Debug)]
+struct DecryptArgs {
+    #[command(flatten)]
+    key: KeyArgs,
+    #[command(flatten)]
+    io: IoArgs,
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+    #[command(flatten)]
+    raw: RawArgs,
+}
+
+fn encrypt(args: &EncryptArgs, defaults: &Defaults) -> Result<(), Box<dyn Error>> {
+    let config = args
+        .options
+        .apply(args.pipeline.config(defaults)?, defaults);
+    let key = args.key.matrix(&config.header())?;
+    let text = args.io.read()?;
+    let en = pipeline::encrypt(&text, &key, &config);
+    let name = match &args.io.input {
+        Some(path) if path.as_os_str() != "-" => path.display().to_string(),
+        _ => "stdin".to_string(),
+    };
+    let output = args.output.output(defaults);
+    if !args.raw.raw {
+        return args.io.write(&output.render(&en.cipher(&config), &name));
+    }
+    let (dna, ambig) = en.raw(&config);
+    let ambig = output.render(&ambig, &format!("{} ambiguity", name));
+    // Both streams at once, so a reader may drain them in either order
+    thread::scope(|scope| {
+        let side = scope.spawn(|| args.raw.write(&ambig));
+        args.io.write(&output.render(&dna, &name))?;
+        Ok(side.join().unwrap_or_else(|e| panic::resume_unwind(e))?)
+    })
+}
+
+/// The cipher to decrypt, joined from the two streams with `--raw`.
+fn read_cipher(args: &DecryptArgs, defaults: &Defaults) -> Result<String, Box<dyn Error>> {
+    if !args.raw.raw {
+        return Ok(defaults::strip_fasta(&args.io.read()?));
+    }
+    let (dna, ambig) = thread::scope(|scope| {
+        let side = scope.spawn(|| args.raw.read());
+        let dna = defaults::strip_fasta(&args.io.read()?);
+        let ambig = side.join().unwrap_or_else(|e| panic::resume_unwind(e))?;
+        Ok::<_, Box<dyn Error>>((dna, ambig))
+    })?;
+    let before = args.pipeline.config(defaults)?.ambig_pos;
+    Ok(playfair::join_raw(&dna, &ambig, before))
+}
+
+fn decrypt(args: &DecryptArgs, defaults: &Defaults, json: bool) -> Result<(), Box<dyn Error>> {
+    let cipher = read_cipher(args, defaults)?;
+    let key = args
+        .key
+        .matrix(&playfair::cipher_header(&cipher).unwrap_or_default())?;
+    let de = pipeline::decrypt(&cipher, &key, &args.pipeline.config(defaults)?);
+    // With --json the diagnostics are part of the error report
+    if !json {
+        for diagnostic in de.diagnostics.iter() {
+            eprintln!("{}", diagnostic);
+        }
+    }
+    match de.status {
+        Status::Empty | Status::Decrypted | Status::Verified => args.io.write(&de.plain_text),
+        status => Err(Failure::decrypt(&status, de.diagnostics).into()),
+    }
+}
+
+fn selftest() -> Result<(), Box<dyn Error>> {
+    let outcomes = selftest::run();
+    let width = outcomes.iter().map(|o| o.case.len()).max().unwrap_or(0);
+    for outcome in outcomes.iter() {
+        let mark = match outcome.passed {
+            true => "ok  ",
+            false => "FAIL",
+        };
+        println!(
+            "{} {:<width$}  {}",
+            mark,
+            outcome.case,
+            outcome.settings,
+            width = width
+        );
+        if !outcome.passed {
+            println!("     {}", outcome.detail);
+        }
+    }
+    let failed = outcomes.iter().filter(|o| !o.passed).count();
+    println!(
+        "playfair-dna {}: {} of {} cases passed",
+        env!("CARGO_PKG_VERSION"),
+        outcomes.len() - failed,
+        outcomes.len()
+    );
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{} cases failed", failed).into()),
+    }
+}
+
+fn run(cli: &Cli, defaults: &Defaults) -> Result<(), Box<dyn Error>> {
+    match &cli.command {
+        Command::Encrypt(args) => encrypt(args, defaults),
+        Command::Decrypt(args) => decrypt(args, defaults, cli.json),
+        Command::Batch(args) => batch::batch(args, defaults),
+        Command::Inspect(args) => inspect::inspect(args, defaults),
+        Command::Keygen(args) => keys::keygen(args),
+        Command::Matrix(args) => keys::matrix(args),
+        Command::Solve(args) => attack::solve(args, defaults),
+        Command::Crack(args) => attack::crack(args, defaults),
+        Command::Selftest => selftest(),
+        Command::Watch(args) => watch::watch(args, defaults),
+        Command::Tui(args) => tui::tui(args, defaults),
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match &cli.config {
+        Some(path) => defaults::load_file(path),
+        None => defaults::load(),
+    }
+    .map_err(|e| e.into())
+    .and_then(|defaults| run(&cli, &defaults));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            match cli.json {
+                true => eprintln!("{}", errors::json(e.as_ref())),
+                false => eprintln!("error: {}", e),
+            }
+            ExitCode::from(errors::kind(e.as_ref()).code())
+        }
+    }
+}
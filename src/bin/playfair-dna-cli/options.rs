@@ -0,0 +1,365 @@
+use crate::errors::{Failure, Kind};
+use clap::Args;
+use playfair_dna::pipeline::defaults::{
+    self, Alphabet, Defaults, DefaultsError, Encoding, Format, Layout, Output, StageName,
+};
+use playfair_dna::pipeline::{Config, Encodings, Stage};
+use playfair_dna::playfair::keygen::{self, KeyStringError};
+use playfair_dna::playfair::{self, adfgx, header::Header, kdf, kdf::KdfParams, nonce};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+pub const KEY_ENV: &str = "PLAYFAIR_DNA_KEY";
+
+#[derive(Args, Debug)]
+pub struct KeyArgs {
+    /// Keyword, shared key string or passphrase
+    #[arg(short, long, group = "key_source")]
+    pub key: Option<String>,
+    /// Environment variable holding the key
+    #[arg(long, value_name = "VAR", group = "key_source")]
+    pub key_env: Option<String>,
+    /// File holding the key; a trailing newline is ignored
+    #[arg(long, value_name = "PATH", group = "key_source")]
+    pub key_file: Option<PathBuf>,
+}
+
+impl KeyArgs {
+    /// The key as given, falling back to `PLAYFAIR_DNA_KEY`.
+    pub fn read(&self) -> Result<String, Box<dyn Error>> {
+        if let Some(key) = &self.key {
+            return Ok(key.clone());
+        }
+        if let Some(path) = &self.key_file {
+            let key = fs::read_to_string(path)
+                .map_err(|e| Failure::io(format!("cannot read key file {}", path.display()), e))?;
+            return Ok(key.trim_end_matches(['\r', '\n']).to_string());
+        }
+        let var = self.key_env.as_deref().unwrap_or(KEY_ENV);
+        std::env::var(var).map_err(|_| {
+            Failure::new(
+                Kind::BadKey,
+                format!(
+                    "no key: pass --key, --key-file or --key-env, or set {}",
+                    var
+                ),
+            )
+            .into()
+        })
+    }
+
+    /// The key matrix for `header`: Argon2 when the header carries KDF
+    /// parameters, a shared key string or keyword otherwise.
+    pub fn matrix(&self, header: &Header) -> Result<Vec<char>, Box<dyn Error>> {
+        resolve(&self.read()?, header)
+    }
+}
+
+/// `KeyArgs::matrix` for a key that has already been read. Unlike the GUI,
+/// a key string with a bad checksum is an error rather than a keyword.
+pub fn resolve(key: &str, header: &Header) -> Result<Vec<char>, Box<dyn Error>> {
+    if key.trim().is_empty() {
+        return Err(Failure::new(Kind::BadKey, "the key is empty").into());
+    }
+    match (&header.kdf, keygen::import_key(key)) {
+        (Some(params), _) => kdf::derive_key_matrix(key, params)
+            .map_err(|e| Failure::new(Kind::BadKey, format!("key derivation error: {}", e)).into()),
+        (None, Ok(matrix)) => Ok(matrix),
+        (None, Err(e @ (KeyStringError::Checksum | KeyStringError::Range))) => {
+            Err(Failure::new(Kind::BadKey, format!("bad key string: {}", e)).into())
+        }
+        (None, Err(_)) => Ok(playfair::generate_key_matrix(key)),
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct IoArgs {
+    /// Input file, stdin if omitted or "-"
+    #[arg(short, long, value_name = "PATH")]
+    pub input: Option<PathBuf>,
+    /// Output file, stdout if omitted or "-"
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+/// An ADFGX keyword that actually moves the coordinates.
+pub fn transposition(keyword: &str) -> Result<String, &'static str> {
+    match adfgx::valid_keyword(keyword) {
+        true => Ok(keyword.to_string()),
+        false => Err(adfgx::KEYWORD_ERROR),
+    }
+}
+
+fn is_stdio(path: &Option<PathBuf>) -> bool {
+    path.as_ref().is_none_or(|p| p.as_os_str() == "-")
+}
+
+impl IoArgs {
+    pub fn read(&self) -> Result<String, Box<dyn Error>> {
+        let mut text = String::new();
+        match &self.input {
+            Some(path) if !is_stdio(&self.input) => {
+                text = fs::read_to_string(path)
+                    .map_err(|e| Failure::io(format!("cannot read {}", path.display()), e))?;
+            }
+            _ => {
+                io::stdin().read_to_string(&mut text)?;
+            }
+        }
+        Ok(text)
+    }
+
+    pub fn write(&self, text: &str) -> Result<(), Box<dyn Error>> {
+        match &self.output {
+            Some(path) if !is_stdio(&self.output) => fs::write(path, text)
+                .map_err(|e| Failure::io(format!("cannot write {}", path.display()), e).into()),
+            _ => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(text.as_bytes())?;
+                Ok(stdout.flush()?)
+            }
+        }
+    }
+}
+
+/// The settings both sides have to agree on, as in `Config`. Unset options
+/// fall back on the config file, then on the built-in defaults.
+#[derive(Args, Debug, Default)]
+pub struct PipelineArgs {
+    /// Where the ambiguity section goes [default: before]
+    #[arg(long, value_enum)]
+    pub layout: Option<Layout>,
+    /// Text encoding of the plaintext [default: utf8]
+    #[arg(long, value_enum)]
+    pub encoding: Option<Encoding>,
+    /// [default: playfair]
+    #[arg(long, value_enum)]
+    pub stage: Option<StageName>,
+    /// Period of the Bifid and Trifid stages [default: 5]
+    #[arg(long)]
+    pub period: Option<usize>,
+    /// Transposition keyword of the ADFGX stage, at least two different
+    /// letters
+    #[arg(
+        long,
+        value_name = "KEYWORD",
+        value_parser = transposition,
+        required_if_eq("stage", "adfgx")
+    )]
+    pub transposition: Option<String>,
+    /// [default: 1]
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=16))]
+    pub rounds: Option<u8>,
+}
+
+impl PipelineArgs {
+    /// The options given on the command line as a config layer.
+    fn layer(&self) -> Defaults {
+        Defaults {
+            layout: self.layout,
+            encoding: self.encoding,
+            stage: self.stage,
+            period: self.period,
+            transposition: self.transposition.clone(),
+            rounds: self.rounds.map(usize::from),
+            ..Defaults::default()
+        }
+    }
+
+    pub fn config(&self, defaults: &Defaults) -> Result<Config, DefaultsError> {
+        defaults.pipeline().merge(self.layer()).config()
+    }
+}
+
+/// Per-message options of the encrypt side; the decrypt side reads them
+/// from the cipher header. Each flag, or its --no- form, overrides the
+/// config file.
+#[derive(Args, Debug)]
+pub struct EncryptOptions {
+    /// Treat the key as a passphrase and stretch it with Argon2
+    #[arg(long, overrides_with = "no_passphrase")]
+    passphrase: bool,
+    /// Use the key as a keyword or key string
+    #[arg(long, overrides_with = "passphrase")]
+    no_passphrase: bool,
+    /// Mask the ambiguity stream with a key-derived keystream; implies
+    /// --nonce
+    #[arg(long, overrides_with = "no_mask_ambig")]
+    mask_ambig: bool,
+    /// Send the ambiguity stream unmasked
+    #[arg(long, overrides_with = "mask_ambig")]
+    no_mask_ambig: bool,
+    /// Derive a fresh message key from a random nonce
+    #[arg(long, overrides_with = "no_nonce")]
+    nonce: bool,
+    /// Encrypt with the key itself; masking still brings a nonce
+    #[arg(long, overrides_with = "nonce")]
+    no_nonce: bool,
+    /// Append an HMAC-SHA256 authentication tag
+    #[arg(long, overrides_with = "no_mac")]
+    mac: bool,
+    /// Leave out the authentication tag
+    #[arg(long, overrides_with = "mac")]
+    no_mac: bool,
+}
+
+/// A flag pair as a config layer: on, off or not given.
+fn switch(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+impl EncryptOptions {
+    /// `config` with a fresh salt and nonce where enabled. Masking always
+    /// brings a nonce.
+    pub fn apply(&self, config: Config, defaults: &Defaults) -> Config {
+        let on = |on: bool, off: bool, key: Option<bool>| switch(on, off).or(key) == Some(true);
+        let mask_ambig = on(self.mask_ambig, self.no_mask_ambig, defaults.mask_ambig);
+        Config {
+            kdf: on(self.passphrase, self.no_passphrase, defaults.passphrase)
+                .then(KdfParams::random),
+            mask_ambig,
+            nonce: (on(self.nonce, self.no_nonce, defaults.nonce) || mask_ambig)
+                .then(nonce::random),
+            mac: on(self.mac, self.no_mac, defaults.mac),
+            ..config
+        }
+    }
+}
+
+/// How ciphers are written; unset options come from the config file.
+#[derive(Args, Debug)]
+pub struct OutputArgs {
+    /// Write U or T for the fourth nucleotide [default: u]
+    #[arg(long, value_enum)]
+    pub alphabet: Option<Alphabet>,
+    /// Nucleotides per line, 0 for a single line [default: 0]
+    #[arg(long, value_name = "WIDTH")]
+    pub wrap: Option<usize>,
+    /// [default: cipher]
+    #[arg(long, value_enum)]
+    pub format: Option<Format>,
+}
+
+impl OutputArgs {
+    pub fn output(&self, defaults: &Defaults) -> Output {
+        let output = defaults.output();
+        Output {
+            alphabet: self.alphabet.unwrap_or(output.alphabet),
+            wrap: self.wrap.unwrap_or(output.wrap),
+            format: self.format.unwrap_or(output.format),
+        }
+    }
+}
+
+/// The DNA alone on the main stream, for pipelines, and the header,
+/// ambiguity stream and tag on a side stream. Diagnostic offsets count in
+/// the two joined back into one cipher.
+#[derive(Args, Debug)]
+pub struct RawArgs {
+    /// Only the DNA goes through the input or output; the rest of the
+    /// cipher goes through --ambig or --ambig-fd
+    #[arg(long, requires = "ambig_side")]
+    pub raw: bool,
+    /// File for the ambiguity side of --raw
+    #[arg(long, value_name = "PATH", requires = "raw", group = "ambig_side")]
+    pub ambig: Option<PathBuf>,
+    /// Open file descriptor for the ambiguity side of --raw, e.g. 3 with
+    /// `3>ambig.dna` (Unix)
+    #[arg(long, value_name = "FD", requires = "raw", group = "ambig_side")]
+    pub ambig_fd: Option<u32>,
+}
+
+impl RawArgs {
+    fn path(&self) -> PathBuf {
+        // Clap requires one of the two with --raw
+        match (&self.ambig, self.ambig_fd) {
+            (Some(path), _) => path.clone(),
+            (None, fd) => PathBuf::from(format!("/dev/fd/{}", fd.unwrap_or_default())),
+        }
+    }
+
+    // Both take a `Failure` rather than a boxed error, so the side stream
+    // can be handled on its own thread while the main stream is.
+
+    pub fn read(&self) -> Result<String, Failure> {
+        let path = self.path();
+        fs::read_to_string(&path)
+            .map(|text| defaults::strip_fasta(&text))
+            .map_err(|e| Failure::io(format!("cannot read {}", path.display()), e))
+    }
+
+    pub fn write(&self, text: &str) -> Result<(), Failure> {
+        let path = self.path();
+        fs::write(&path, text)
+            .map_err(|e| Failure::io(format!("cannot write {}", path.display()), e))
+    }
+}
+
+/// A `Config` as written to reports and manifests. Salts and nonces are
+/// per message and only recorded as switched on or off. The ADFGX
+/// transposition keyword is key material and left out.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Settings {
+    pub layout: Layout,
+    pub encoding: Encoding,
+    pub stage: StageName,
+    pub period: Option<usize>,
+    pub rounds: usize,
+    pub kdf: bool,
+    pub mask_ambig: bool,
+    pub nonce: bool,
+    pub mac: bool,
+}
+
+impl Settings {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            layout: match config.ambig_pos {
+                true => Layout::Before,
+                false => Layout::After,
+            },
+            encoding: match config.text_format {
+                Encodings::UTF8 => Encoding::Utf8,
+                Encodings::UTF16 => Encoding::Utf16,
+            },
+            stage: match config.stage {
+                Stage::Playfair => StageName::Playfair,
+                Stage::Bifid(_) => StageName::Bifid,
+                Stage::PlayfairBifid(_) => StageName::PlayfairBifid,
+                Stage::Trifid(_) => StageName::Trifid,
+                Stage::Adfgx(_) => StageName::Adfgx,
+            },
+            period: config.stage.period(),
+            rounds: config.rounds,
+            kdf: config.kdf.is_some(),
+            mask_ambig: config.mask_ambig,
+            nonce: config.nonce.is_some(),
+            mac: config.mac,
+        }
+    }
+
+    /// The decrypt side `Config`; the header supplies the rest, and the
+    /// caller the ADFGX keyword. Config files do not apply, the settings are
+    /// complete.
+    pub fn config(&self, transposition: Option<&str>) -> Result<Config, Box<dyn Error>> {
+        let rounds = u8::try_from(self.rounds)
+            .map_err(|_| format!("{} rounds, at most {} are supported", self.rounds, u8::MAX))?;
+        let config = PipelineArgs {
+            layout: Some(self.layout),
+            encoding: Some(self.encoding),
+            stage: Some(self.stage),
+            period: self.period,
+            transposition: transposition.map(str::to_string),
+            rounds: Some(rounds),
+        }
+        .config(&Defaults::default())?;
+        Ok(config)
+    }
+}
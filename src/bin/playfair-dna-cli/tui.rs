@@ -0,0 +1,601 @@
+use crate::options::{EncryptOptions, PipelineArgs};
+use clap::Args;
+use playfair_dna::pipeline::defaults::{self, Defaults, Output};
+use playfair_dna::pipeline::{self, Config, Decrypted, Encrypted, Status};
+use playfair_dna::playfair::{self, header::Header, kdf, kdf::KdfParams, keygen, strength};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use ratatui::Frame;
+use std::error::Error;
+use std::ops::Range;
+
+// The two panes of the GUI in a terminal, for machines without a display.
+// Every keystroke reruns the pipeline like the GUI's text fields do.
+
+const MARK_COLOR: Color = Color::Red;
+const HELP: &str =
+    " Tab next field · ^G generate key · ^E stages · ^K matrix · ^Y cipher to decrypt · Esc quit ";
+
+#[derive(Args, Debug)]
+pub struct TuiArgs {
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+    #[command(flatten)]
+    options: EncryptOptions,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    EnKey,
+    EnPlain,
+    DeKey,
+    DeCipher,
+}
+
+impl Field {
+    fn next(self) -> Self {
+        match self {
+            Field::EnKey => Field::EnPlain,
+            Field::EnPlain => Field::DeKey,
+            Field::DeKey => Field::DeCipher,
+            Field::DeCipher => Field::EnKey,
+        }
+    }
+
+    fn previous(self) -> Self {
+        self.next().next().next()
+    }
+}
+
+/// A text field with a cursor, counted in chars.
+#[derive(Default)]
+struct Editor {
+    text: String,
+    cursor: usize,
+}
+
+impl Editor {
+    fn byte(&self, chars: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(chars)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    fn set(&mut self, text: String) {
+        self.cursor = text.chars().count();
+        self.text = text;
+    }
+
+    /// Applies an editing key, true if the text changed.
+    fn handle(&mut self, key: KeyEvent, multiline: bool) -> bool {
+        let len = self.text.chars().count();
+        match key.code {
+            KeyCode::Char(c) => {
+                let i = self.byte(self.cursor);
+                self.text.insert(i, c);
+                self.cursor += 1;
+                true
+            }
+            KeyCode::Enter if multiline => {
+                let i = self.byte(self.cursor);
+                self.text.insert(i, '\n');
+                self.cursor += 1;
+                true
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.text.remove(self.byte(self.cursor));
+                true
+            }
+            KeyCode::Delete if self.cursor < len => {
+                self.text.remove(self.byte(self.cursor));
+                true
+            }
+            KeyCode::Left => {
+                self.cursor = self.cursor.saturating_sub(1);
+                false
+            }
+            KeyCode::Right => {
+                self.cursor = (self.cursor + 1).min(len);
+                false
+            }
+            KeyCode::Home => {
+                self.cursor = 0;
+                false
+            }
+            KeyCode::End => {
+                self.cursor = len;
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+/// `text` with the `marked` char ranges in the mark color and, when focused,
+/// the cursor as a reversed cell.
+fn styled<'a>(text: &str, marked: &[Range<usize>], cursor: Option<usize>) -> Text<'a> {
+    let mut lines: Vec<Line> = vec![Line::default()];
+    let chars: Vec<char> = text.chars().collect();
+    for i in 0..=chars.len() {
+        let mut style = Style::default();
+        if marked.iter().any(|r| r.contains(&i)) {
+            style = style.fg(MARK_COLOR).add_modifier(Modifier::BOLD);
+        }
+        if cursor == Some(i) {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        match chars.get(i) {
+            Some('\n') => {
+                if cursor == Some(i) {
+                    lines
+                        .last_mut()
+                        .unwrap()
+                        .push_span(Span::styled(" ", style));
+                }
+                lines.push(Line::default());
+            }
+            Some(c) => lines
+                .last_mut()
+                .unwrap()
+                .push_span(Span::styled(c.to_string(), style)),
+            None if cursor == Some(i) => lines
+                .last_mut()
+                .unwrap()
+                .push_span(Span::styled(" ", style)),
+            None => {}
+        }
+    }
+    Text::from(lines)
+}
+
+fn block(title: String, focused: bool) -> Block<'static> {
+    let style = match focused {
+        true => Style::default().fg(Color::Yellow),
+        false => Style::default(),
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(title)
+}
+
+fn matrix_rows(matrix: &[char]) -> Text<'static> {
+    Text::from(
+        matrix
+            .chunks(5)
+            .map(|row| {
+                let cells: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+                Line::from(format!(" {} ", cells.join(" ")))
+            })
+            .collect::<Vec<Line>>(),
+    )
+}
+
+fn settings(config: &Config) -> String {
+    let mut line = format!(
+        " {} x{} · ambiguity {} · {}",
+        config.stage.string(),
+        config.rounds,
+        match config.ambig_pos {
+            true => "before",
+            false => "after",
+        },
+        config.text_format.string()
+    );
+    for (on, name) in [
+        (config.kdf.is_some(), "passphrase"),
+        (config.mask_ambig, "masked"),
+        (config.nonce.is_some(), "nonce"),
+        (config.mac, "tag"),
+    ] {
+        if on {
+            line += " · ";
+            line += name;
+        }
+    }
+    line
+}
+
+fn digits(ambig: &[u8]) -> String {
+    ambig.iter().map(|digit| digit.to_string()).collect()
+}
+
+fn bits(binary: &[u8]) -> String {
+    binary
+        .iter()
+        .map(|byte| format!("{:08b}", byte))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn stages<'a>(rows: &[(&str, String)]) -> Text<'a> {
+    Text::from(
+        rows.iter()
+            .map(|(label, value)| {
+                Line::from(vec![
+                    Span::styled(format!("{:<10} ", label), Style::default().fg(Color::Cyan)),
+                    Span::raw(value.clone()),
+                ])
+            })
+            .collect::<Vec<Line>>(),
+    )
+}
+
+/// The key matrix for `key`, through Argon2 when `params` is set. The last
+/// derived matrix is cached so typing the text does not rerun Argon2.
+fn key_matrix(
+    key: &str,
+    params: Option<&KdfParams>,
+    cache: &mut Option<(String, KdfParams, Vec<char>)>,
+) -> Vec<char> {
+    let Some(params) = params else {
+        return keygen::resolve_key(key);
+    };
+    if let Some((cached_key, cached_params, matrix)) = cache {
+        if cached_key == key && cached_params == params {
+            return matrix.clone();
+        }
+    }
+    let matrix = kdf::derive_key_matrix(key, params).unwrap_or_default();
+    *cache = Some((key.to_string(), params.clone(), matrix.clone()));
+    matrix
+}
+
+struct Tui {
+    en_config: Config,
+    de_config: Config,
+    output: Output,
+    en_key: Editor,
+    en_plain: Editor,
+    de_key: Editor,
+    de_cipher: Editor,
+    en_matrix: Vec<char>,
+    de_matrix: Vec<char>,
+    de_header: Header,
+    en_kdf_cache: Option<(String, KdfParams, Vec<char>)>,
+    de_kdf_cache: Option<(String, KdfParams, Vec<char>)>,
+    en: Encrypted,
+    en_cipher: String,
+    de: Decrypted,
+    focus: Field,
+    show_stages: bool,
+    show_matrix: bool,
+}
+
+impl Tui {
+    fn encrypt(&mut self) {
+        self.en_config.renew_nonce();
+        self.en_matrix = key_matrix(
+            &self.en_key.text,
+            self.en_config.kdf.as_ref(),
+            &mut self.en_kdf_cache,
+        );
+        self.en = pipeline::encrypt(&self.en_plain.text, &self.en_matrix, &self.en_config);
+        self.en_cipher = match self.en_plain.text.is_empty() {
+            true => String::new(),
+            false => self
+                .output
+                .render(&self.en.cipher(&self.en_config), "message")
+                .trim_end()
+                .to_string(),
+        };
+    }
+
+    fn decrypt(&mut self) {
+        let cipher = defaults::strip_fasta(&self.de_cipher.text);
+        self.de_header = playfair::cipher_header(&cipher).unwrap_or_default();
+        self.de_matrix = key_matrix(
+            &self.de_key.text,
+            self.de_header.kdf.as_ref(),
+            &mut self.de_kdf_cache,
+        );
+        self.de = pipeline::decrypt(&cipher, &self.de_matrix, &self.de_config);
+    }
+
+    /// False once the user quits.
+    fn handle(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return false,
+            KeyCode::Char('c') if ctrl => return false,
+            KeyCode::Tab => self.focus = self.focus.next(),
+            KeyCode::BackTab => self.focus = self.focus.previous(),
+            KeyCode::Char('e') if ctrl => self.show_stages = !self.show_stages,
+            KeyCode::Char('k') if ctrl => self.show_matrix = !self.show_matrix,
+            KeyCode::Char('y') if ctrl => {
+                self.de_cipher.set(self.en_cipher.clone());
+                self.decrypt();
+            }
+            KeyCode::Char('g') if ctrl => {
+                let key = keygen::export_key(&keygen::random_key_matrix()).unwrap_or_default();
+                match self.focus {
+                    Field::EnKey | Field::EnPlain => {
+                        self.en_key.set(key);
+                        self.encrypt();
+                    }
+                    Field::DeKey | Field::DeCipher => {
+                        self.de_key.set(key);
+                        self.decrypt();
+                    }
+                }
+            }
+            _ if ctrl => {}
+            _ => {
+                let changed = match self.focus {
+                    Field::EnKey => self.en_key.handle(key, false),
+                    Field::EnPlain => self.en_plain.handle(key, true),
+                    Field::DeKey => self.de_key.handle(key, false),
+                    Field::DeCipher => self.de_cipher.handle(key, true),
+                };
+                match (changed, self.focus) {
+                    (false, _) => {}
+                    (true, Field::EnKey | Field::EnPlain) => self.encrypt(),
+                    (true, Field::DeKey | Field::DeCipher) => self.decrypt(),
+                }
+            }
+        }
+        true
+    }
+
+    fn cursor(&self, field: Field, editor: &Editor) -> Option<usize> {
+        (self.focus == field).then_some(editor.cursor)
+    }
+
+    fn key_title(&self, key: &str, matrix: &[char], passphrase: bool) -> String {
+        let strength = match passphrase {
+            true => strength::analyze_passphrase(key, matrix),
+            false => strength::analyze_key(key),
+        };
+        format!(" Key · {} ", strength.rating().string())
+    }
+
+    fn draw_encrypt(&self, frame: &mut Frame, area: Rect) {
+        let mut constraints = vec![Constraint::Length(1), Constraint::Length(3)];
+        if self.show_matrix {
+            constraints.push(Constraint::Length(7));
+        }
+        constraints.extend([Constraint::Fill(1), Constraint::Fill(1)]);
+        if self.show_stages {
+            constraints.push(Constraint::Fill(1));
+        }
+        let areas = Layout::vertical(constraints).split(area);
+        let mut areas = areas.iter();
+
+        frame.render_widget(
+            Paragraph::new(settings(&self.en_config)).style(Style::default().fg(Color::DarkGray)),
+            *areas.next().unwrap(),
+        );
+        frame.render_widget(
+            Paragraph::new(styled(
+                &self.en_key.text,
+                &[],
+                self.cursor(Field::EnKey, &self.en_key),
+            ))
+            .block(block(
+                self.key_title(
+                    &self.en_key.text,
+                    &self.en_matrix,
+                    self.en_config.kdf.is_some(),
+                ),
+                self.focus == Field::EnKey,
+            )),
+            *areas.next().unwrap(),
+        );
+        if self.show_matrix {
+            frame.render_widget(
+                Paragraph::new(matrix_rows(&self.en_matrix))
+                    .block(block(" Key matrix ".to_string(), false)),
+                *areas.next().unwrap(),
+            );
+        }
+        frame.render_widget(
+            Paragraph::new(styled(
+                &self.en_plain.text,
+                &[],
+                self.cursor(Field::EnPlain, &self.en_plain),
+            ))
+            .wrap(Wrap { trim: false })
+            .block(block(
+                " Plain Text ".to_string(),
+                self.focus == Field::EnPlain,
+            )),
+            *areas.next().unwrap(),
+        );
+        frame.render_widget(
+            Paragraph::new(self.en_cipher.clone())
+                .wrap(Wrap { trim: false })
+                .block(block(" Cipher (encrypted DNA + Ambig) ".to_string(), false)),
+            *areas.next().unwrap(),
+        );
+        if self.show_stages {
+            let rows = [
+                ("Binary", bits(&self.en.binary)),
+                ("DNA", self.en.dna.clone()),
+                ("Acids", self.en.acids.clone()),
+                ("Ambig", digits(&self.en.ambig_vec)),
+                ("enc Acids", self.en.acids_after_playfair.clone()),
+                ("enc DNA", self.en.dna_after_playfair.clone()),
+            ];
+            frame.render_widget(
+                Paragraph::new(stages(&rows))
+                    .wrap(Wrap { trim: false })
+                    .block(block(" Extra ".to_string(), false)),
+                *areas.next().unwrap(),
+            );
+        }
+    }
+
+    fn draw_decrypt(&self, frame: &mut Frame, area: Rect) {
+        let report = self.de.diagnostics.len() as u16 + (self.de.status != Status::Empty) as u16;
+        let mut constraints = vec![Constraint::Length(1), Constraint::Length(3)];
+        if self.show_matrix {
+            constraints.push(Constraint::Length(7));
+        }
+        constraints.extend([
+            Constraint::Fill(1),
+            Constraint::Length(report.min(6)),
+            Constraint::Fill(1),
+        ]);
+        if self.show_stages {
+            constraints.push(Constraint::Fill(1));
+        }
+        let areas = Layout::vertical(constraints).split(area);
+        let mut areas = areas.iter();
+
+        frame.render_widget(
+            Paragraph::new(settings(&self.de_config)).style(Style::default().fg(Color::DarkGray)),
+            *areas.next().unwrap(),
+        );
+        let passphrase = self.de_header.kdf.is_some();
+        frame.render_widget(
+            Paragraph::new(styled(
+                &self.de_key.text,
+                &[],
+                self.cursor(Field::DeKey, &self.de_key),
+            ))
+            .block(block(
+                self.key_title(&self.de_key.text, &self.de_matrix, passphrase),
+                self.focus == Field::DeKey,
+            )),
+            *areas.next().unwrap(),
+        );
+        if self.show_matrix {
+            frame.render_widget(
+                Paragraph::new(matrix_rows(&self.de_matrix))
+                    .block(block(" Key matrix ".to_string(), false)),
+                *areas.next().unwrap(),
+            );
+        }
+        let marked: Vec<Range<usize>> = self
+            .de
+            .diagnostics
+            .iter()
+            .flat_map(|d| d.offsets.clone())
+            .collect();
+        frame.render_widget(
+            Paragraph::new(styled(
+                &self.de_cipher.text,
+                &marked,
+                self.cursor(Field::DeCipher, &self.de_cipher),
+            ))
+            .wrap(Wrap { trim: false })
+            .block(block(
+                " Cipher (encrypted DNA + Ambig) ".to_string(),
+                self.focus == Field::DeCipher,
+            )),
+            *areas.next().unwrap(),
+        );
+
+        let mut report: Vec<Line> = Vec::new();
+        if self.de.status != Status::Empty {
+            let color = match self.de.status {
+                Status::Verified => Color::Green,
+                Status::Decrypted => Color::Reset,
+                _ => MARK_COLOR,
+            };
+            report.push(Line::styled(
+                format!(" {}", self.de.status.string()),
+                Style::default().fg(color),
+            ));
+        }
+        for d in self.de.diagnostics.iter() {
+            let causes: Vec<&str> = d.causes.iter().map(|c| c.string()).collect();
+            report.push(Line::styled(
+                format!(
+                    " {}: {} (likely {})",
+                    d.stage.string(),
+                    d.message,
+                    causes.join(", ")
+                ),
+                Style::default().fg(MARK_COLOR),
+            ));
+        }
+        frame.render_widget(Paragraph::new(report), *areas.next().unwrap());
+
+        frame.render_widget(
+            Paragraph::new(self.de.plain_text.clone())
+                .wrap(Wrap { trim: false })
+                .block(block(" Plain Text ".to_string(), false)),
+            *areas.next().unwrap(),
+        );
+        if self.show_stages {
+            let rows = [
+                ("DNA", self.de.dna.clone()),
+                ("Ambig", digits(&self.de.ambig_vec)),
+                ("Acids", self.de.acids.clone()),
+                ("dec Acids", self.de.acids_after_playfair.clone()),
+                ("dec DNA", self.de.dna_after_playfair.clone()),
+                ("Binary", bits(&self.de.binary)),
+            ];
+            frame.render_widget(
+                Paragraph::new(stages(&rows))
+                    .wrap(Wrap { trim: false })
+                    .block(block(" Extra ".to_string(), false)),
+                *areas.next().unwrap(),
+            );
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [panes, help] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(panes);
+        let left_block = Block::default().borders(Borders::RIGHT).title(" Encrypt ");
+        let right_block = Block::default().title(" Decrypt ");
+        self.draw_encrypt(frame, left_block.inner(left));
+        self.draw_decrypt(frame, right_block.inner(right));
+        frame.render_widget(left_block, left);
+        frame.render_widget(right_block, right);
+        frame.render_widget(
+            Paragraph::new(HELP).style(Style::default().add_modifier(Modifier::REVERSED)),
+            help,
+        );
+    }
+}
+
+pub fn tui(args: &TuiArgs, defaults: &Defaults) -> Result<(), Box<dyn Error>> {
+    let mut tui = Tui {
+        en_config: args
+            .options
+            .apply(args.pipeline.config(defaults)?, defaults),
+        de_config: args.pipeline.config(defaults)?,
+        output: defaults.output(),
+        en_key: Editor::default(),
+        en_plain: Editor::default(),
+        de_key: Editor::default(),
+        de_cipher: Editor::default(),
+        en_matrix: Vec::new(),
+        de_matrix: Vec::new(),
+        de_header: Header::default(),
+        en_kdf_cache: None,
+        de_kdf_cache: None,
+        en: Encrypted::default(),
+        en_cipher: String::new(),
+        de: Decrypted::default(),
+        focus: Field::EnKey,
+        show_stages: false,
+        show_matrix: true,
+    };
+    tui.encrypt();
+    tui.decrypt();
+
+    let mut terminal = ratatui::init();
+    let result = (|| -> Result<(), Box<dyn Error>> {
+        loop {
+            terminal.draw(|frame| tui.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !tui.handle(key) {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,280 @@
+use crate::errors::Failure;
+use crate::options::{self, EncryptOptions, KeyArgs, OutputArgs, PipelineArgs};
+use clap::Args;
+use notify::event::{AccessKind, AccessMode, EventKind};
+use notify::{RecursiveMode, Watcher};
+use playfair_dna::pipeline;
+use playfair_dna::pipeline::defaults::{Defaults, Format};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Encrypts every file that appears in a directory. Files are picked up once
+// they have been quiet for the settle time, so a copy still in progress is
+// not read half-written. Inputs are moved aside afterwards, processed or
+// failed, and a signal stops the loop between two files.
+
+const POLL: Duration = Duration::from_millis(200);
+
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    /// Directory to watch; only files directly inside are encrypted
+    input_dir: PathBuf,
+    /// Directory for the ciphers, outside INPUT_DIR
+    output_dir: PathBuf,
+    #[command(flatten)]
+    key: KeyArgs,
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+    #[command(flatten)]
+    options: EncryptOptions,
+    #[command(flatten)]
+    output: OutputArgs,
+    /// Where encrypted inputs are moved [default: INPUT_DIR/processed]
+    #[arg(long, value_name = "DIR")]
+    processed_dir: Option<PathBuf>,
+    /// Where inputs that could not be encrypted are moved
+    /// [default: INPUT_DIR/failed]
+    #[arg(long, value_name = "DIR")]
+    failed_dir: Option<PathBuf>,
+    /// Also append failures to this file
+    #[arg(long, value_name = "PATH")]
+    log: Option<PathBuf>,
+    /// Milliseconds a file has to stay unchanged before it is read
+    #[arg(long, value_name = "MS", default_value_t = 500)]
+    settle: u64,
+}
+
+struct Watch<'a> {
+    args: &'a WatchArgs,
+    /// Canonical, as the notifications report paths.
+    input_dir: PathBuf,
+    output_dir: PathBuf,
+    defaults: &'a Defaults,
+    key: String,
+    processed_dir: PathBuf,
+    failed_dir: PathBuf,
+    encrypted: usize,
+    failed: usize,
+}
+
+/// A non-hidden regular file directly inside `dir`.
+fn is_candidate(dir: &Path, path: &Path) -> bool {
+    path.parent() == Some(dir)
+        && path
+            .file_name()
+            .is_some_and(|name| !name.to_string_lossy().starts_with('.'))
+        && fs::symlink_metadata(path).is_ok_and(|meta| meta.is_file())
+}
+
+/// `dir/name`, or `dir/stem.N.ext` with the first free N, so a file dropped
+/// again under the same name does not replace the earlier one.
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if fs::symlink_metadata(&path).is_err() {
+        return path;
+    }
+    let name = Path::new(name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    (1..)
+        .map(|n| match name.extension() {
+            Some(ext) => dir.join(format!("{}.{}.{}", stem, n, ext.to_string_lossy())),
+            None => dir.join(format!("{}.{}", stem, n)),
+        })
+        .find(|path| fs::symlink_metadata(path).is_err())
+        .unwrap()
+}
+
+/// `path` moved into `dir`, under a new name if taken.
+fn move_into(path: &Path, dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let name = path.file_name().ok_or("no file name")?.to_string_lossy();
+    let target = unused_path(dir, &name);
+    fs::rename(path, &target)
+        .map_err(|e| format!("cannot move {} to {}: {}", path.display(), dir.display(), e))?;
+    Ok(target)
+}
+
+impl Watch<'_> {
+    fn encrypt(&self, path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        let name = path.file_name().ok_or("no file name")?.to_string_lossy();
+        let bytes = fs::read(path).map_err(|e| format!("cannot read: {}", e))?;
+        let text = String::from_utf8(bytes).map_err(|_| "not UTF-8 text")?;
+        let config = self
+            .args
+            .options
+            .apply(self.args.pipeline.config(self.defaults)?, self.defaults);
+        let matrix = options::resolve(&self.key, &config.header())?;
+        let cipher = pipeline::encrypt(&text, &matrix, &config).cipher(&config);
+
+        let mut output = self.args.output.output(self.defaults);
+        if self.args.output.format.is_none() && self.defaults.format.is_none() {
+            output.format = Format::Fasta;
+        }
+        let target = unused_path(
+            &self.output_dir,
+            &format!("{}.{}", name, output.format.extension()),
+        );
+        // Readers of the output directory never see a partial cipher
+        let partial = self.output_dir.join(format!(".{}.partial", name));
+        fs::write(&partial, output.render(&cipher, &name))
+            .and_then(|()| fs::rename(&partial, &target))
+            .map_err(|e| format!("cannot write {}: {}", target.display(), e))?;
+        Ok(target)
+    }
+
+    fn log_failure(&self, path: &Path, error: &str) {
+        eprintln!("error: {}: {}", path.display(), error);
+        let Some(log) = &self.args.log else {
+            return;
+        };
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_secs());
+        let written = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log)
+            .and_then(|mut file| writeln!(file, "{}\t{}\t{}", time, path.display(), error));
+        if let Err(e) = written {
+            eprintln!("error: cannot write {}: {}", log.display(), e);
+        }
+    }
+
+    fn process(&mut self, path: &Path) {
+        if !is_candidate(&self.input_dir, path) {
+            return;
+        }
+        let result = self.encrypt(path).and_then(|target| {
+            move_into(path, &self.processed_dir)?;
+            Ok(target)
+        });
+        match result {
+            Ok(target) => {
+                eprintln!("{} -> {}", path.display(), target.display());
+                self.encrypted += 1;
+            }
+            Err(e) => {
+                self.log_failure(path, &e.to_string());
+                if let Err(e) = move_into(path, &self.failed_dir) {
+                    self.log_failure(path, &e.to_string());
+                }
+                self.failed += 1;
+            }
+        }
+    }
+}
+
+/// `dir` created if missing, as a canonical path.
+fn create_dir(dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    fs::create_dir_all(dir)
+        .and_then(|()| fs::canonicalize(dir))
+        .map_err(|e| Failure::io(format!("cannot create {}", dir.display()), e).into())
+}
+
+pub fn watch(args: &WatchArgs, defaults: &Defaults) -> Result<(), Box<dyn Error>> {
+    let input_dir = fs::canonicalize(&args.input_dir)
+        .map_err(|e| Failure::io(format!("cannot watch {}", args.input_dir.display()), e))?;
+    let output_dir = create_dir(&args.output_dir)?;
+    // Ciphers written where they are watched would be encrypted again and
+    // again
+    if output_dir.starts_with(&input_dir) {
+        return Err(format!(
+            "the output directory {} is inside the input directory {}",
+            output_dir.display(),
+            input_dir.display()
+        )
+        .into());
+    }
+    let processed_dir = create_dir(
+        &args
+            .processed_dir
+            .clone()
+            .unwrap_or_else(|| input_dir.join("processed")),
+    )?;
+    let failed_dir = create_dir(
+        &args
+            .failed_dir
+            .clone()
+            .unwrap_or_else(|| input_dir.join("failed")),
+    )?;
+    for dir in [&processed_dir, &failed_dir] {
+        if *dir == input_dir {
+            return Err(format!(
+                "{} is the input directory, inputs cannot be moved there",
+                dir.display()
+            )
+            .into());
+        }
+    }
+    let mut watch = Watch {
+        args,
+        defaults,
+        key: args.key.read()?,
+        input_dir,
+        output_dir,
+        processed_dir,
+        failed_dir,
+        encrypted: 0,
+        failed: 0,
+    };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT] {
+        signal_hook::flag::register(signal, Arc::clone(&stop))?;
+    }
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&watch.input_dir, RecursiveMode::NonRecursive)?;
+    eprintln!("watching {}", watch.input_dir.display());
+
+    // Files already waiting count as new, then every change restarts the
+    // settle time of its file.
+    let settle = Duration::from_millis(args.settle);
+    let mut pending: HashMap<PathBuf, Instant> = fs::read_dir(&watch.input_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| (entry.path(), Instant::now())))
+        .collect();
+    while !stop.load(Ordering::Relaxed) {
+        match events.recv_timeout(POLL) {
+            Ok(Ok(event)) => match event.kind {
+                EventKind::Create(_)
+                | EventKind::Modify(_)
+                | EventKind::Access(AccessKind::Close(AccessMode::Write)) => {
+                    for path in event.paths {
+                        pending.insert(path, Instant::now());
+                    }
+                }
+                EventKind::Remove(_) => {
+                    for path in event.paths.iter() {
+                        pending.remove(path);
+                    }
+                }
+                _ => {}
+            },
+            Ok(Err(e)) => eprintln!("error: {}", e),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        let ready: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, changed)| changed.elapsed() >= settle)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in ready {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            pending.remove(&path);
+            watch.process(&path);
+        }
+    }
+    eprintln!(
+        "stopped, {} files encrypted, {} failed",
+        watch.encrypted, watch.failed
+    );
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use crate::playfair;
+
+pub mod anneal;
+pub mod avalanche;
+pub mod dictionary;
+pub mod frequency;
+pub mod known_plaintext;
+
+// Reference text for the statistics. Plain English prose, pushed through the
+// same binary -> DNA -> acid path as a message would be.
+const CORPUS: &str = "It was late in the afternoon when the letter finally \
+arrived, and by then most of the people in the laboratory had already gone \
+home. The envelope was thin and the handwriting on the front was careful, as \
+if the writer had been worried that the address would be misread. Inside \
+there was a single sheet of paper covered in long rows of letters that made \
+no sense at first glance. She read it twice, then put it down on the bench \
+next to the microscope and went to make a cup of tea. When she came back the \
+page was still there, of course, and it still made no sense. The letters \
+were grouped in fives, which suggested a cipher, and the same few groups \
+appeared again and again near the top of the page. That was the first \
+mistake the sender had made. A message that repeats itself gives away more \
+than its author intends, because every repetition is a small window into \
+the structure underneath. She took out a pencil and started counting how \
+often each letter appeared, writing the totals in a column along the edge \
+of the page. The most common letters were not the ones she expected, but \
+the pattern was clear enough: this was not random noise. Someone had \
+written an ordinary message in an ordinary language and then hidden it \
+behind a key. If the key was short, or if it was a word that could be \
+guessed, the message would not stay hidden for long. Over the next hour she \
+tried the obvious candidates, the name of the project, the name of the \
+street, the year the building was opened, and each time she compared the \
+result with what an English sentence should look like. Most attempts \
+produced nothing but noise. One of them, however, produced a fragment that \
+looked almost like a word, and that was enough to keep going. By the time \
+the cleaners arrived the whole message was written out in plain text on \
+the back of the envelope, and it turned out to be an invitation to dinner.";
+
+/// Log-probabilities of acid bigrams, add-one smoothed. Three bytes make
+/// four acids, so the statistics are kept per position modulo four.
+pub struct AcidStats {
+    bigrams: Vec<f64>,
+}
+
+pub fn acid_index(c: char) -> Option<usize> {
+    playfair::ALPHABET.chars().position(|a| a == c)
+}
+
+impl AcidStats {
+    /// Statistics of the acid stream that English UTF-8 text produces.
+    pub fn english() -> Self {
+        Self::from_text(CORPUS)
+    }
+
+    pub fn from_text(text: &str) -> Self {
+        let dna = playfair::binary_to_dna(&playfair::utf8_to_binary(text));
+        let (acids, _) = playfair::dna_to_acids(&dna);
+        let mut counts = vec![1f64; 4 * 25 * 25];
+        let indices: Vec<usize> = acids.iter().filter_map(|&c| acid_index(c)).collect();
+        for (i, pair) in indices.windows(2).enumerate() {
+            counts[(i % 4) * 625 + pair[0] * 25 + pair[1]] += 1.0;
+        }
+        let mut bigrams = vec![0f64; counts.len()];
+        for phase in 0..4 {
+            let range = phase * 625..(phase + 1) * 625;
+            let total: f64 = counts[range.clone()].iter().sum();
+            for i in range {
+                bigrams[i] = (counts[i] / total).ln();
+            }
+        }
+        Self { bigrams }
+    }
+
+    /// Mean log-probability per bigram of an acid string, given as alphabet
+    /// indices and starting on a byte boundary.
+    pub fn score(&self, acids: &[usize]) -> f64 {
+        if acids.len() < 2 {
+            return 0.0;
+        }
+        let sum: f64 = acids
+            .windows(2)
+            .enumerate()
+            .map(|(i, pair)| self.bigrams[(i % 4) * 625 + pair[0] * 25 + pair[1]])
+            .sum();
+        sum / (acids.len() - 1) as f64
+    }
+}
+
+/// Share of the bytes that decode as valid UTF-8 and are not control
+/// characters (line breaks and tabs count as text).
+pub fn utf8_score(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let mut good = 0usize;
+    for chunk in bytes.utf8_chunks() {
+        good += chunk
+            .valid()
+            .chars()
+            .filter(|c| !c.is_control() || c.is_whitespace())
+            .map(|c| c.len_utf8())
+            .sum::<usize>();
+    }
+    good as f64 / bytes.len() as f64
+}
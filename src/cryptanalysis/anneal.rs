@@ -0,0 +1,312 @@
+use super::{acid_index, utf8_score, AcidStats};
+use crate::playfair;
+
+// Ciphertext-only attack on a single Playfair round. Candidate matrices are
+// perturbed by simulated annealing and every candidate decryption is scored by
+// how much its acid stream looks like encoded text and how much of the
+// resulting binary decodes as UTF-8.
+
+pub struct AnnealParams {
+    pub iterations: usize,
+    pub restarts: usize,
+    pub start_temperature: f64,
+    pub utf8_weight: f64,
+    pub report_every: usize,
+    /// Seed of the search, for reproducible runs; random if `None`.
+    pub seed: Option<u64>,
+}
+
+impl Default for AnnealParams {
+    fn default() -> Self {
+        Self {
+            iterations: 50_000,
+            restarts: 8,
+            start_temperature: 8.0,
+            utf8_weight: 6.0,
+            report_every: 5_000,
+            seed: None,
+        }
+    }
+}
+
+pub struct Progress {
+    pub restart: usize,
+    pub iteration: usize,
+    pub temperature: f64,
+    pub best_score: f64,
+    pub best_key: Vec<char>,
+    pub best_text: String,
+}
+
+#[derive(Clone)]
+pub struct Candidate {
+    pub key: Vec<char>,
+    pub score: f64,
+    pub bytes: Vec<u8>,
+}
+
+impl Candidate {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).into_owned()
+    }
+}
+
+// xorshift64*, the attack only needs cheap, decent randomness
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| {
+            let mut seed = [0u8; 8];
+            getrandom::getrandom(&mut seed).expect("OS random number generator unavailable");
+            u64::from_le_bytes(seed)
+        });
+        Rng(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// The fixed part of the problem: cipher acids, filler marks and the codon
+/// table, all as small integers.
+pub struct Target {
+    acids: Vec<usize>,
+    fillers: Vec<bool>,
+    ambig: Vec<u8>,
+    codons: Vec<Option<[u8; 3]>>,
+    stats: AcidStats,
+}
+
+impl Target {
+    /// `dna` and `ambig` as returned by `split_cipher`.
+    pub fn new(dna: &[char], ambig: &[u8]) -> Option<Target> {
+        let (acids, _) = playfair::dna_to_acids(dna);
+        if acids.len() != ambig.len() || !acids.len().is_multiple_of(2) {
+            return None;
+        }
+        let acids: Option<Vec<usize>> = acids.iter().map(|&c| acid_index(c)).collect();
+
+        let alphabet: Vec<char> = playfair::ALPHABET.chars().collect();
+        let mut codons: Vec<Option<[u8; 3]>> = Vec::with_capacity(25 * 4);
+        for acid in alphabet.iter() {
+            for a in 0..4u8 {
+                codons.push(playfair::codon(*acid, a));
+            }
+        }
+        Some(Target {
+            acids: acids?,
+            fillers: ambig.iter().map(|&a| a == 4).collect(),
+            ambig: ambig.iter().copied().filter(|&a| a != 4).collect(),
+            codons,
+            stats: AcidStats::english(),
+        })
+    }
+
+    /// Playfair decryption on alphabet indices; `key[cell]` is an acid index.
+    fn decrypt(&self, key: &[usize]) -> Vec<usize> {
+        let mut cell = [0usize; 25];
+        for (i, &k) in key.iter().enumerate() {
+            cell[k] = i;
+        }
+        let mut plain: Vec<usize> = Vec::with_capacity(self.acids.len());
+        for pair in self.acids.chunks(2) {
+            let (i1, i2) = (cell[pair[0]], cell[pair[1]]);
+            let (r1, c1, r2, c2) = (i1 / 5, i1 % 5, i2 / 5, i2 % 5);
+            if r1 == r2 {
+                plain.push(key[r1 * 5 + (c1 + 4) % 5]);
+                plain.push(key[r2 * 5 + (c2 + 4) % 5]);
+            } else if c1 == c2 {
+                plain.push(key[(r1 + 4) % 5 * 5 + c1]);
+                plain.push(key[(r2 + 4) % 5 * 5 + c2]);
+            } else {
+                plain.push(key[r1 * 5 + c2]);
+                plain.push(key[r2 * 5 + c1]);
+            }
+        }
+        plain
+            .into_iter()
+            .zip(self.fillers.iter())
+            .filter(|(_, &filler)| !filler)
+            .map(|(acid, _)| acid)
+            .collect()
+    }
+
+    /// Decrypted binary plus the share of acid/ambiguity pairs that name no codon.
+    fn binary(&self, acids: &[usize]) -> (Vec<u8>, f64) {
+        let mut invalid = 0usize;
+        let mut nucleotides: Vec<u8> = Vec::with_capacity(acids.len() * 3);
+        for (acid, ambig) in acids.iter().zip(self.ambig.iter()) {
+            match self
+                .codons
+                .get(acid * 4 + *ambig as usize)
+                .copied()
+                .flatten()
+            {
+                Some(codon) => nucleotides.extend_from_slice(&codon),
+                None => {
+                    invalid += 1;
+                    nucleotides.extend_from_slice(&[0, 0, 0]);
+                }
+            }
+        }
+        let bytes = nucleotides
+            .chunks_exact(4)
+            .map(|n| n[0] << 6 | n[1] << 4 | n[2] << 2 | n[3])
+            .collect();
+        (bytes, invalid as f64 / acids.len().max(1) as f64)
+    }
+
+    fn score(&self, key: &[usize], utf8_weight: f64) -> (f64, Vec<u8>) {
+        let acids = self.decrypt(key);
+        let (bytes, invalid) = self.binary(&acids);
+        let score = self.stats.score(&acids) + utf8_weight * (utf8_score(&bytes) - invalid);
+        (score * acids.len() as f64, bytes)
+    }
+}
+
+fn perturb(key: &mut [usize], rng: &mut Rng) {
+    match rng.below(50) {
+        0 => {
+            let (a, b) = (rng.below(5), rng.below(5));
+            for c in 0..5 {
+                key.swap(a * 5 + c, b * 5 + c);
+            }
+        }
+        1 => {
+            let (a, b) = (rng.below(5), rng.below(5));
+            for r in 0..5 {
+                key.swap(r * 5 + a, r * 5 + b);
+            }
+        }
+        2 => key.reverse(),
+        _ => key.swap(rng.below(25), rng.below(25)),
+    }
+}
+
+fn to_chars(key: &[usize]) -> Vec<char> {
+    let alphabet: Vec<char> = playfair::ALPHABET.chars().collect();
+    key.iter().map(|&k| alphabet[k]).collect()
+}
+
+/// Searches for the key matrix of a Playfair cipher given only the cipher.
+/// `progress` is called every `report_every` iterations with the best
+/// candidate found so far.
+pub fn anneal(
+    dna: &[char],
+    ambig: &[u8],
+    params: &AnnealParams,
+    mut progress: impl FnMut(&Progress),
+) -> Option<Candidate> {
+    let target = Target::new(dna, ambig)?;
+    let mut rng = Rng::new(params.seed);
+    let mut best: Option<(Vec<usize>, f64, Vec<u8>)> = None;
+
+    for restart in 0..params.restarts.max(1) {
+        let mut key: Vec<usize> = (0..25).collect();
+        for i in (1..25).rev() {
+            key.swap(i, rng.below(i + 1));
+        }
+        let (mut score, bytes) = target.score(&key, params.utf8_weight);
+        if best.as_ref().is_none_or(|b| score > b.1) {
+            best = Some((key.clone(), score, bytes));
+        }
+
+        for iteration in 0..params.iterations {
+            let temperature =
+                params.start_temperature * (1.0 - iteration as f64 / params.iterations as f64);
+            let mut candidate = key.clone();
+            perturb(&mut candidate, &mut rng);
+            let (candidate_score, bytes) = target.score(&candidate, params.utf8_weight);
+            let delta = candidate_score - score;
+            if delta >= 0.0 || rng.unit() < (delta / temperature.max(1e-9)).exp() {
+                key = candidate;
+                score = candidate_score;
+                if best.as_ref().is_none_or(|b| score > b.1) {
+                    best = Some((key.clone(), score, bytes));
+                }
+            }
+
+            if params.report_every > 0 && (iteration + 1) % params.report_every == 0 {
+                let (best_key, best_score, best_bytes) = best.as_ref().unwrap();
+                progress(&Progress {
+                    restart,
+                    iteration: iteration + 1,
+                    temperature,
+                    best_score: *best_score,
+                    best_key: to_chars(best_key),
+                    best_text: String::from_utf8_lossy(best_bytes).into_owned(),
+                });
+            }
+        }
+    }
+
+    best.map(|(key, score, bytes)| Candidate {
+        key: to_chars(&key),
+        score,
+        bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{self, Config};
+
+    const TEXT: &str = "The annealing attack should find its way to a matrix that turns this \
+                        short English message back into readable text, or close to it.";
+
+    fn run(dna: &[char], ambig: &[u8], seed: u64) -> (Vec<f64>, Candidate) {
+        let params = AnnealParams {
+            iterations: 4_000,
+            restarts: 1,
+            report_every: 500,
+            seed: Some(seed),
+            ..AnnealParams::default()
+        };
+        let mut scores: Vec<f64> = Vec::new();
+        let best = anneal(dna, ambig, &params, |p| scores.push(p.best_score)).unwrap();
+        (scores, best)
+    }
+
+    #[test]
+    fn annealing_improves_the_score_reproducibly() {
+        let key = playfair::generate_key_matrix("annealing");
+        let config = Config::default();
+        let cipher = pipeline::encrypt(TEXT, &key, &config).cipher(&config);
+        let (dna, ambig) = playfair::split_cipher(&cipher, config.ambig_pos);
+
+        let target = Target::new(&dna, &ambig).unwrap();
+        let start: Vec<usize> = (0..25).collect();
+        let (start_score, _) = target.score(&start, AnnealParams::default().utf8_weight);
+
+        let (scores, best) = run(&dna, &ambig, 7);
+        assert_eq!(scores.len(), 8);
+        assert!(scores.windows(2).all(|w| w[0] <= w[1]), "{:?}", scores);
+        assert!(best.score > start_score);
+        assert_eq!(Some(&best.score), scores.last());
+
+        // The same seed takes the same path
+        let (again, other) = run(&dna, &ambig, 7);
+        assert_eq!(again, scores);
+        assert_eq!(other.key, best.key);
+    }
+
+    #[test]
+    fn ciphers_that_do_not_pair_up_are_refused() {
+        assert!(Target::new(&['A', 'A', 'A'], &[0]).is_none());
+        assert!(Target::new(&"AAAGGG".chars().collect::<Vec<char>>(), &[0]).is_none());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::pipeline::{self, Config};
+use crate::playfair;
+use rayon::prelude::*;
+use std::io::{self, Write};
+
+// Diffusion measurements. Every message of a corpus is encrypted once as is
+// and once per single-bit change of the plaintext or single-letter change of
+// the keyword, and the cipher streams are compared position by position.
+// Extra or missing symbols (fillers move) count as changed.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flip {
+    PlaintextBit,
+    KeyLetter,
+}
+
+impl Flip {
+    pub fn string(&self) -> &str {
+        match self {
+            Flip::PlaintextBit => "plaintext bit",
+            Flip::KeyLetter => "key letter",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub mode: String,
+    pub flip: Flip,
+    pub message: usize,
+    /// Bit index into the plaintext or letter index into the keyword.
+    pub position: usize,
+    pub nucleotides_changed: usize,
+    pub nucleotides_total: usize,
+    pub ambig_changed: usize,
+    pub ambig_total: usize,
+}
+
+impl Sample {
+    pub fn nucleotide_rate(&self) -> f64 {
+        self.nucleotides_changed as f64 / self.nucleotides_total.max(1) as f64
+    }
+
+    pub fn ambig_rate(&self) -> f64 {
+        self.ambig_changed as f64 / self.ambig_total.max(1) as f64
+    }
+}
+
+pub struct Summary {
+    pub mode: String,
+    pub flip: Flip,
+    pub samples: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub ambig_mean: f64,
+}
+
+/// Changed positions between two streams and the length of the longer one.
+fn difference<T: PartialEq>(a: &[T], b: &[T]) -> (usize, usize) {
+    let same = a.iter().zip(b.iter()).filter(|(x, y)| x == y).count();
+    let total = a.len().max(b.len());
+    (total - same, total)
+}
+
+fn streams(text: &str, key: &[char], config: &Config) -> (Vec<char>, Vec<u8>) {
+    let en = pipeline::encrypt(text, key, config);
+    (en.dna_after_playfair.chars().collect(), en.cipher_ambig)
+}
+
+// The next letter of the alphabet, wrapping around, so the keyword really changes
+fn replace_letter(keyword: &[char], i: usize) -> String {
+    let alphabet: Vec<char> = playfair::ALPHABET.chars().collect();
+    let current = alphabet
+        .iter()
+        .position(|&a| a == keyword[i].to_ascii_uppercase())
+        .unwrap_or(0);
+    let mut changed = keyword.to_vec();
+    changed[i] = alphabet[(current + 1) % alphabet.len()];
+    changed.iter().collect()
+}
+
+/// Runs both kinds of flips over every message. Plaintext bits are taken
+/// every `bit_step` bits; flips that leave invalid UTF-8 are skipped. The
+/// nonce and tag settings of `config` are ignored.
+pub fn measure(
+    mode: &str,
+    config: &Config,
+    keyword: &str,
+    messages: &[String],
+    bit_step: usize,
+) -> Vec<Sample> {
+    let config = Config {
+        nonce: None,
+        mac: false,
+        ..config.clone()
+    };
+    let key = playfair::generate_key_matrix(keyword);
+    messages
+        .par_iter()
+        .enumerate()
+        .flat_map(|(message, text)| {
+            let (dna, ambig) = streams(text, &key, &config);
+            let sample =
+                |flip: Flip,
+                 position: usize,
+                 (flipped_dna, flipped_ambig): (Vec<char>, Vec<u8>)| {
+                    let (nucleotides_changed, nucleotides_total) = difference(&dna, &flipped_dna);
+                    let (ambig_changed, ambig_total) = difference(&ambig, &flipped_ambig);
+                    Sample {
+                        mode: mode.to_string(),
+                        flip,
+                        message,
+                        position,
+                        nucleotides_changed,
+                        nucleotides_total,
+                        ambig_changed,
+                        ambig_total,
+                    }
+                };
+
+            let mut samples: Vec<Sample> = Vec::new();
+            let bytes = text.as_bytes();
+            for bit in (0..bytes.len() * 8).step_by(bit_step.max(1)) {
+                let mut flipped = bytes.to_vec();
+                flipped[bit / 8] ^= 0x80 >> (bit % 8);
+                if let Ok(flipped) = String::from_utf8(flipped) {
+                    samples.push(sample(
+                        Flip::PlaintextBit,
+                        bit,
+                        streams(&flipped, &key, &config),
+                    ));
+                }
+            }
+            let letters: Vec<char> = keyword
+                .chars()
+                .filter(|c| c.is_ascii_alphabetic())
+                .collect();
+            for i in 0..letters.len() {
+                let changed = playfair::generate_key_matrix(&replace_letter(&letters, i));
+                samples.push(sample(Flip::KeyLetter, i, streams(text, &changed, &config)));
+            }
+            samples
+        })
+        .collect()
+}
+
+/// Mean, spread and range of the nucleotide change rate per mode and flip kind.
+pub fn summarize(samples: &[Sample]) -> Vec<Summary> {
+    let mut groups: Vec<(String, Flip)> = Vec::new();
+    for s in samples.iter() {
+        if !groups.contains(&(s.mode.clone(), s.flip)) {
+            groups.push((s.mode.clone(), s.flip));
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(mode, flip)| {
+            let group: Vec<&Sample> = samples
+                .iter()
+                .filter(|s| s.mode == mode && s.flip == flip)
+                .collect();
+            let n = group.len().max(1) as f64;
+            let rates: Vec<f64> = group.iter().map(|s| s.nucleotide_rate()).collect();
+            let mean = rates.iter().sum::<f64>() / n;
+            let variance = rates.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+            Summary {
+                samples: group.len(),
+                mean,
+                std_dev: variance.sqrt(),
+                min: rates.iter().copied().fold(f64::INFINITY, f64::min),
+                max: rates.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                ambig_mean: group.iter().map(|s| s.ambig_rate()).sum::<f64>() / n,
+                mode,
+                flip,
+            }
+        })
+        .collect()
+}
+
+pub fn write_csv(samples: &[Sample], mut out: impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "mode,flip,message,position,nucleotides_changed,nucleotides_total,ambig_changed,ambig_total"
+    )?;
+    for s in samples.iter() {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{}",
+            s.mode,
+            s.flip.string(),
+            s.message,
+            s.position,
+            s.nucleotides_changed,
+            s.nucleotides_total,
+            s.ambig_changed,
+            s.ambig_total
+        )?;
+    }
+    Ok(())
+}
+
+/// The reference corpus of the language statistics, one sentence per message.
+pub fn corpus() -> Vec<String> {
+    super::CORPUS
+        .split_inclusive(['.', ':'])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
@@ -0,0 +1,338 @@
+use super::{acid_index, utf8_score, AcidStats};
+use crate::pipeline::{self, Config, Status};
+use crate::playfair::{self, kdf};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::{fs, io, path::Path};
+
+// Wordlist attack on keyword-derived keys. Every candidate goes through the
+// full decrypt pipeline, so headers, nonces, masks, tags, stages and rounds
+// are handled exactly as on the decrypt side. Keywords that give the same
+// matrix are only tried once; with a KDF header every spelling is distinct.
+
+const LEET: [(char, char); 7] = [
+    ('A', '4'),
+    ('E', '3'),
+    ('I', '1'),
+    ('O', '0'),
+    ('S', '5'),
+    ('T', '7'),
+    ('B', '8'),
+];
+
+const MAX_LEET_POSITIONS: usize = 6;
+
+pub struct DictionaryParams {
+    /// Also try lower case, upper case and capitalized spellings.
+    pub case_variants: bool,
+    /// Also try every word with letters swapped for look-alike digits and back.
+    pub leetspeak: bool,
+    /// Stop as soon as a candidate scores at least this much; a verified tag
+    /// always stops the search.
+    pub stop_score: Option<f64>,
+    /// Number of best guesses to keep.
+    pub keep: usize,
+    pub report_every: usize,
+    pub utf8_weight: f64,
+}
+
+impl Default for DictionaryParams {
+    fn default() -> Self {
+        Self {
+            case_variants: false,
+            leetspeak: false,
+            stop_score: None,
+            keep: 10,
+            report_every: 1_000,
+            utf8_weight: 6.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Guess {
+    pub word: String,
+    pub key: Vec<char>,
+    pub score: f64,
+    pub status: Status,
+    pub text: String,
+}
+
+pub struct Progress<'a> {
+    pub tried: usize,
+    pub total: usize,
+    pub best: Option<&'a Guess>,
+}
+
+/// One word per line; blank lines and surrounding whitespace are ignored.
+pub fn load_wordlist(path: &Path) -> io::Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect())
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(|c| c.to_lowercase()))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+fn to_digit(c: char) -> Option<char> {
+    LEET.iter()
+        .find(|(letter, _)| *letter == c.to_ascii_uppercase())
+        .map(|(_, digit)| *digit)
+}
+
+fn to_letter(c: char) -> Option<char> {
+    LEET.iter()
+        .find(|(_, digit)| *digit == c)
+        .map(|(letter, _)| *letter)
+}
+
+/// Every mix of plain and swapped characters when there are at most
+/// `MAX_LEET_POSITIONS` candidates for a swap, only the fully swapped word
+/// otherwise. The word itself is left to the caller.
+fn leet(word: &str) -> Vec<String> {
+    let chars: Vec<char> = word.chars().collect();
+    let swaps: Vec<(usize, char)> = chars
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &c)| to_digit(c).or_else(|| to_letter(c)).map(|s| (i, s)))
+        .collect();
+    let swapped = |mask: &dyn Fn(usize) -> bool| -> String {
+        let mut variant = chars.clone();
+        for (bit, (i, swapped)) in swaps.iter().enumerate() {
+            if mask(bit) {
+                variant[*i] = *swapped;
+            }
+        }
+        variant.iter().collect()
+    };
+    if swaps.len() > MAX_LEET_POSITIONS {
+        return vec![swapped(&|_| true)];
+    }
+    (1..1u32 << swaps.len())
+        .map(|mask| swapped(&|bit| mask & (1 << bit) != 0))
+        .collect()
+}
+
+pub fn variants(word: &str, params: &DictionaryParams) -> Vec<String> {
+    let mut variants: Vec<String> = vec![word.to_string()];
+    if params.case_variants {
+        variants.extend([word.to_lowercase(), word.to_uppercase(), capitalize(word)]);
+    }
+    if params.leetspeak {
+        let spelled: Vec<String> = variants.clone();
+        for variant in spelled.iter() {
+            variants.extend(leet(variant));
+        }
+    }
+    let mut seen: HashSet<String> = HashSet::new();
+    variants.retain(|v| seen.insert(v.clone()));
+    variants
+}
+
+/// Candidates to try: `(word, matrix)` for keyword ciphers, deduplicated by
+/// matrix, or bare words when the matrix has to come from the KDF.
+fn candidates(
+    words: &[String],
+    params: &DictionaryParams,
+    derived: bool,
+) -> Vec<(String, Option<Vec<char>>)> {
+    let mut seen: HashSet<Vec<char>> = HashSet::new();
+    let mut candidates: Vec<(String, Option<Vec<char>>)> = Vec::new();
+    for word in words.iter() {
+        for variant in variants(word, params) {
+            if derived {
+                candidates.push((variant, None));
+                continue;
+            }
+            let key = playfair::generate_key_matrix(&variant);
+            if seen.insert(key.clone()) {
+                candidates.push((variant, Some(key)));
+            }
+        }
+    }
+    candidates
+}
+
+fn score(de: &pipeline::Decrypted, stats: &AcidStats, utf8_weight: f64) -> f64 {
+    let acids: Vec<usize> = de
+        .acids_after_playfair
+        .chars()
+        .filter_map(acid_index)
+        .collect();
+    stats.score(&acids) + utf8_weight * utf8_score(&de.binary)
+}
+
+/// Tries every word of the list (and its variants) as the key of `cipher`
+/// and returns the best guesses, best first. `progress` is called from the
+/// worker threads every `report_every` candidates; setting `stop` ends the
+/// search early.
+pub fn crack(
+    cipher: &str,
+    config: &Config,
+    words: &[String],
+    params: &DictionaryParams,
+    stop: &AtomicBool,
+    progress: impl Fn(&Progress) + Sync,
+) -> Vec<Guess> {
+    let kdf_params = playfair::cipher_header(cipher).and_then(|header| header.kdf);
+    let candidates = candidates(words, params, kdf_params.is_some());
+    let stats = AcidStats::english();
+    let tried = AtomicUsize::new(0);
+    let best: Mutex<Vec<Guess>> = Mutex::new(Vec::new());
+
+    candidates.par_iter().for_each(|(word, key)| {
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        let key = match (key, &kdf_params) {
+            (Some(key), _) => key.clone(),
+            (None, Some(kdf_params)) => match kdf::derive_key_matrix(word, kdf_params) {
+                Ok(key) => key,
+                Err(_) => return,
+            },
+            (None, None) => playfair::generate_key_matrix(word),
+        };
+        let de = pipeline::decrypt(cipher, &key, config);
+        let guess = Guess {
+            word: word.clone(),
+            score: match de.status {
+                Status::Verified => f64::INFINITY,
+                Status::BadTag | Status::Malformed(_) => f64::NEG_INFINITY,
+                _ => score(&de, &stats, params.utf8_weight),
+            },
+            key,
+            status: de.status,
+            text: String::from_utf8_lossy(&de.binary).into_owned(),
+        };
+        if guess.status == Status::Verified
+            || params
+                .stop_score
+                .is_some_and(|stop_score| guess.score >= stop_score)
+        {
+            stop.store(true, Ordering::Relaxed);
+        }
+
+        let mut best = best.lock().unwrap();
+        if best.len() < params.keep.max(1) || guess.score > best.last().unwrap().score {
+            best.push(guess);
+            best.sort_by(|a, b| b.score.total_cmp(&a.score));
+            best.truncate(params.keep.max(1));
+        }
+        let tried = tried.fetch_add(1, Ordering::Relaxed) + 1;
+        if params.report_every > 0 && tried.is_multiple_of(params.report_every) {
+            progress(&Progress {
+                tried,
+                total: candidates.len(),
+                best: best.first(),
+            });
+        }
+    });
+
+    best.into_inner().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Config;
+    use crate::playfair;
+
+    const TEXT: &str = "Meet me by the old bridge at seven tonight and bring the letters \
+                        with you, nobody else must see them before the morning train.";
+    const WORDS: [&str; 6] = ["apple", "harbour", "Lantern", "orchid", "violet", "walnut"];
+
+    #[test]
+    fn leet_mixes_every_swap_up_to_the_cap() {
+        let mut variants = leet("Sea");
+        variants.sort();
+        assert_eq!(variants, ["534", "53a", "5e4", "5ea", "S34", "S3a", "Se4"]);
+        assert!(leet("xyz").is_empty());
+        assert_eq!(leet("b00k").len(), 7);
+    }
+
+    #[test]
+    fn leet_swaps_all_or_nothing_above_the_cap() {
+        // More swappable letters than a u32 mask has bits
+        let word = "TEST".repeat(12);
+        assert_eq!(leet(&word), ["7357".repeat(12)]);
+        assert_eq!(leet("abstaine"), ["485741n3"]);
+        let params = DictionaryParams {
+            leetspeak: true,
+            ..DictionaryParams::default()
+        };
+        assert_eq!(variants(&word, &params), [word.clone(), "7357".repeat(12)]);
+    }
+
+    #[test]
+    fn variants_are_tried_once() {
+        let params = DictionaryParams {
+            case_variants: true,
+            ..DictionaryParams::default()
+        };
+        assert_eq!(variants("Word", &params), ["Word", "word", "WORD"]);
+        let words: Vec<String> = ["Keyword", "KEYWORD", "keyword"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        // Case does not change the matrix
+        assert_eq!(candidates(&words, &params, false).len(), 1);
+        // Every spelling is its own passphrase
+        assert_eq!(candidates(&words, &params, true).len(), 9);
+    }
+
+    fn crack_with(config: &Config, keyword: &str) -> Vec<Guess> {
+        let key = playfair::generate_key_matrix(keyword);
+        let cipher = pipeline::encrypt(TEXT, &key, config).cipher(config);
+        let words: Vec<String> = WORDS.iter().map(|w| w.to_string()).collect();
+        let params = DictionaryParams {
+            case_variants: true,
+            keep: 3,
+            ..DictionaryParams::default()
+        };
+        crack(
+            &cipher,
+            config,
+            &words,
+            &params,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+    }
+
+    #[test]
+    fn the_right_keyword_ranks_first() {
+        let guesses = crack_with(&Config::default(), "LANTERN");
+        assert_eq!(guesses.len(), 3);
+        assert_eq!(
+            playfair::generate_key_matrix(&guesses[0].word),
+            playfair::generate_key_matrix("lantern")
+        );
+        assert!(guesses[0].text.starts_with(TEXT));
+        assert!(guesses[0].score > guesses[1].score);
+    }
+
+    #[test]
+    fn a_verified_tag_stops_the_search() {
+        let config = Config {
+            mac: true,
+            ..Config::default()
+        };
+        let guesses = crack_with(&config, "orchid");
+        assert_eq!(guesses[0].word, "orchid");
+        assert_eq!(guesses[0].status, Status::Verified);
+        assert!(guesses[0].text.starts_with(TEXT));
+    }
+}
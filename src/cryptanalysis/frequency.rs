@@ -0,0 +1,105 @@
+use crate::playfair;
+
+// Letter statistics for the analysis panel. All functions take the strings
+// the pipeline already produces and skip characters that do not belong.
+
+const NUCLEOTIDES: [char; 4] = ['A', 'C', 'G', 'U'];
+
+pub struct Histogram {
+    pub labels: Vec<String>,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    fn new(labels: Vec<String>) -> Self {
+        let counts = vec![0; labels.len()];
+        Self { labels, counts }
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub fn max(&self) -> usize {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Probability that two symbols drawn without replacement are equal.
+    /// About 0.04 for uniform acids, higher for anything language-like.
+    pub fn index_of_coincidence(&self) -> f64 {
+        let total = self.total();
+        if total < 2 {
+            return 0.0;
+        }
+        let pairs: usize = self.counts.iter().map(|&n| n * n.saturating_sub(1)).sum();
+        pairs as f64 / (total * (total - 1)) as f64
+    }
+}
+
+fn nucleotide_index(c: char) -> Option<usize> {
+    NUCLEOTIDES.iter().position(|&n| n == c)
+}
+
+pub fn nucleotides(dna: &str) -> Histogram {
+    let mut histogram = Histogram::new(NUCLEOTIDES.iter().map(|n| n.to_string()).collect());
+    for i in dna.chars().filter_map(nucleotide_index) {
+        histogram.counts[i] += 1;
+    }
+    histogram
+}
+
+/// All 64 codons in AAA, AAC, ... order, read in frame from the first nucleotide.
+pub fn codons(dna: &str) -> Histogram {
+    let mut labels: Vec<String> = Vec::with_capacity(64);
+    for a in NUCLEOTIDES {
+        for b in NUCLEOTIDES {
+            for c in NUCLEOTIDES {
+                labels.push([a, b, c].iter().collect());
+            }
+        }
+    }
+    let mut histogram = Histogram::new(labels);
+    let indices: Vec<usize> = dna.chars().filter_map(nucleotide_index).collect();
+    for codon in indices.chunks_exact(3) {
+        histogram.counts[codon[0] * 16 + codon[1] * 4 + codon[2]] += 1;
+    }
+    histogram
+}
+
+pub fn acids(acids: &str) -> Histogram {
+    let mut histogram = Histogram::new(playfair::ALPHABET.chars().map(|c| c.to_string()).collect());
+    for i in acids.chars().filter_map(super::acid_index) {
+        histogram.counts[i] += 1;
+    }
+    histogram
+}
+
+/// The `top` most frequent digraphs, counted the way Playfair pairs the acids.
+pub fn digraphs(acids: &str, top: usize) -> Histogram {
+    let indices: Vec<usize> = acids.chars().filter_map(super::acid_index).collect();
+    let mut counts = vec![0usize; 25 * 25];
+    for pair in indices.chunks_exact(2) {
+        counts[pair[0] * 25 + pair[1]] += 1;
+    }
+    let mut ranked: Vec<usize> = (0..counts.len()).filter(|&i| counts[i] > 0).collect();
+    ranked.sort_by_key(|&i| std::cmp::Reverse(counts[i]));
+    ranked.truncate(top);
+
+    let alphabet: Vec<char> = playfair::ALPHABET.chars().collect();
+    Histogram {
+        labels: ranked
+            .iter()
+            .map(|&i| [alphabet[i / 25], alphabet[i % 25]].iter().collect())
+            .collect(),
+        counts: ranked.iter().map(|&i| counts[i]).collect(),
+    }
+}
+
+/// Share of G and C among the nucleotides.
+pub fn gc_content(dna: &str) -> f64 {
+    let histogram = nucleotides(dna);
+    match histogram.total() {
+        0 => 0.0,
+        total => (histogram.counts[1] + histogram.counts[2]) as f64 / total as f64,
+    }
+}
@@ -0,0 +1,314 @@
+use super::acid_index;
+use crate::playfair;
+use std::fmt;
+
+// Known-plaintext reconstruction of a single Playfair round. Every aligned
+// digraph pins the four letters into a row, a column or a rectangle of the
+// matrix; a backtracking search places letters cell by cell and propagates
+// those rules in both directions. A matrix and its cyclic row/column shifts
+// encrypt identically, so the most constrained letter is fixed in the corner.
+
+#[derive(Debug, PartialEq)]
+pub enum AlignmentError {
+    Length { plain: usize, cipher: usize },
+    Symbol(char),
+}
+
+impl fmt::Display for AlignmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlignmentError::Length { plain, cipher } => write!(
+                f,
+                "plaintext gives {} acids but the cipher has {} - wrong plaintext or layout?",
+                plain, cipher
+            ),
+            AlignmentError::Symbol(c) => write!(f, "'{}' is not in the acid alphabet", c),
+        }
+    }
+}
+
+impl std::error::Error for AlignmentError {}
+
+/// A plaintext digraph and the cipher digraph it encrypts to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Digraph {
+    pub plain: (char, char),
+    pub cipher: (char, char),
+}
+
+/// Runs the plaintext through the pipeline up to the Playfair input and pairs
+/// it with the cipher acids. `dna` and `ambig` come from `split_cipher`.
+pub fn align(text: &str, dna: &[char], ambig: &[u8]) -> Result<Vec<Digraph>, AlignmentError> {
+    let plain_dna = playfair::binary_to_dna(&playfair::utf8_to_binary(text));
+    let (plain_acids, mut plain_ambig) = playfair::dna_to_acids(&plain_dna);
+    let plain = playfair::insert_fillers(&plain_acids.iter().collect::<String>(), &mut plain_ambig);
+    let (cipher, _) = playfair::dna_to_acids(dna);
+    if plain.len() != cipher.len() || cipher.len() != ambig.len() {
+        return Err(AlignmentError::Length {
+            plain: plain.len(),
+            cipher: cipher.len(),
+        });
+    }
+    let plain: Vec<char> = plain.chars().collect();
+    Ok(plain
+        .chunks(2)
+        .zip(cipher.chunks(2))
+        .map(|(p, c)| Digraph {
+            plain: (p[0], p[1]),
+            cipher: (c[0], c[1]),
+        })
+        .collect())
+}
+
+pub struct Reconstruction {
+    /// Consistent matrices, one per shift class; `None` marks cells the
+    /// digraphs say nothing about.
+    pub candidates: Vec<Vec<Option<char>>>,
+    /// False when the search stopped at the candidate limit or node budget.
+    pub complete: bool,
+    pub digraphs: usize,
+}
+
+impl Reconstruction {
+    pub fn format(candidate: &[Option<char>]) -> String {
+        candidate
+            .chunks(5)
+            .map(|row| {
+                row.iter()
+                    .map(|c| c.unwrap_or('?').to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+const NODE_BUDGET: usize = 2_000_000;
+
+struct Search {
+    digraphs: Vec<[usize; 4]>,
+    order: Vec<usize>,
+    limit: usize,
+    nodes: usize,
+    candidates: Vec<Vec<Option<char>>>,
+}
+
+#[derive(Clone)]
+struct Grid {
+    pos: [Option<usize>; 25],
+    cell: [Option<usize>; 25],
+}
+
+impl Grid {
+    fn place(&mut self, letter: usize, cell: usize) -> bool {
+        match (self.pos[letter], self.cell[cell]) {
+            (Some(p), _) => p == cell,
+            (None, Some(_)) => false,
+            (None, None) => {
+                self.pos[letter] = Some(cell);
+                self.cell[cell] = Some(letter);
+                true
+            }
+        }
+    }
+}
+
+type CellRule = fn(usize, usize) -> (usize, usize);
+
+fn encrypt_cells(a: usize, b: usize) -> (usize, usize) {
+    let (r1, c1, r2, c2) = (a / 5, a % 5, b / 5, b % 5);
+    if r1 == r2 {
+        (r1 * 5 + (c1 + 1) % 5, r2 * 5 + (c2 + 1) % 5)
+    } else if c1 == c2 {
+        ((r1 + 1) % 5 * 5 + c1, (r2 + 1) % 5 * 5 + c2)
+    } else {
+        (r1 * 5 + c2, r2 * 5 + c1)
+    }
+}
+
+fn decrypt_cells(a: usize, b: usize) -> (usize, usize) {
+    let (r1, c1, r2, c2) = (a / 5, a % 5, b / 5, b % 5);
+    if r1 == r2 {
+        (r1 * 5 + (c1 + 4) % 5, r2 * 5 + (c2 + 4) % 5)
+    } else if c1 == c2 {
+        ((r1 + 4) % 5 * 5 + c1, (r2 + 4) % 5 * 5 + c2)
+    } else {
+        (r1 * 5 + c2, r2 * 5 + c1)
+    }
+}
+
+impl Search {
+    /// Applies the Playfair rule to every digraph with a known half until
+    /// nothing changes; false on contradiction.
+    fn propagate(&self, grid: &mut Grid) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &[p1, p2, c1, c2] in self.digraphs.iter() {
+                let (from, to, rule): (_, _, CellRule) =
+                    match (grid.pos[p1], grid.pos[p2], grid.pos[c1], grid.pos[c2]) {
+                        (Some(a), Some(b), _, _) => ((a, b), (c1, c2), encrypt_cells),
+                        (_, _, Some(a), Some(b)) => ((a, b), (p1, p2), decrypt_cells),
+                        _ => continue,
+                    };
+                let (e1, e2) = rule(from.0, from.1);
+                let known = (grid.pos[to.0], grid.pos[to.1]);
+                if !grid.place(to.0, e1) || !grid.place(to.1, e2) {
+                    return false;
+                }
+                changed |= known != (grid.pos[to.0], grid.pos[to.1]);
+            }
+        }
+        true
+    }
+
+    fn solve(&mut self, grid: Grid, depth: usize) {
+        if self.candidates.len() >= self.limit || self.nodes >= NODE_BUDGET {
+            return;
+        }
+        self.nodes += 1;
+        let next = self.order[depth..]
+            .iter()
+            .position(|&letter| grid.pos[letter].is_none());
+        let letter = match next {
+            Some(i) => self.order[depth + i],
+            None => {
+                let alphabet: Vec<char> = playfair::ALPHABET.chars().collect();
+                self.candidates
+                    .push(grid.cell.iter().map(|c| c.map(|l| alphabet[l])).collect());
+                return;
+            }
+        };
+        let depth = depth + next.unwrap();
+        let cells: Vec<usize> = match grid.pos.iter().all(|p| p.is_none()) {
+            true => vec![0],
+            false => (0..25).filter(|&c| grid.cell[c].is_none()).collect(),
+        };
+        for cell in cells {
+            let mut branch = grid.clone();
+            if branch.place(letter, cell) && self.propagate(&mut branch) {
+                self.solve(branch, depth + 1);
+            }
+        }
+    }
+}
+
+/// Lists the key matrices (up to `limit`, modulo row/column shifts) that
+/// encrypt every digraph of `text` to the matching digraph of the cipher.
+pub fn reconstruct(
+    text: &str,
+    dna: &[char],
+    ambig: &[u8],
+    limit: usize,
+) -> Result<Reconstruction, AlignmentError> {
+    let aligned = align(text, dna, ambig)?;
+    let mut digraphs: Vec<[usize; 4]> = Vec::with_capacity(aligned.len());
+    let mut frequency = [0usize; 25];
+    for d in aligned.iter() {
+        let mut letters = [0usize; 4];
+        for (slot, c) in letters
+            .iter_mut()
+            .zip([d.plain.0, d.plain.1, d.cipher.0, d.cipher.1])
+        {
+            *slot = acid_index(c).ok_or(AlignmentError::Symbol(c))?;
+            frequency[*slot] += 1;
+        }
+        if !digraphs.contains(&letters) {
+            digraphs.push(letters);
+        }
+    }
+    let mut order: Vec<usize> = (0..25).filter(|&l| frequency[l] > 0).collect();
+    order.sort_by_key(|&l| std::cmp::Reverse(frequency[l]));
+
+    let mut search = Search {
+        digraphs,
+        order,
+        limit,
+        nodes: 0,
+        candidates: Vec::new(),
+    };
+    search.solve(
+        Grid {
+            pos: [None; 25],
+            cell: [None; 25],
+        },
+        0,
+    );
+    let complete = search.candidates.len() < limit && search.nodes < NODE_BUDGET;
+    Ok(Reconstruction {
+        candidates: search.candidates,
+        complete,
+        digraphs: aligned.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{self, Config};
+    use crate::playfair::ALPHABET;
+
+    const TEXT: &str = "It was the best of times, it was the worst of times, it was the age \
+                        of wisdom, it was the age of foolishness, it was the epoch of belief, \
+                        it was the epoch of incredulity, it was the season of Light, it was \
+                        the season of Darkness.";
+
+    /// `candidate` with its open cells filled by the letters it lacks.
+    fn filled(candidate: &[Option<char>]) -> Vec<char> {
+        let mut missing = ALPHABET.chars().filter(|c| !candidate.contains(&Some(*c)));
+        candidate
+            .iter()
+            .map(|cell| cell.unwrap_or_else(|| missing.next().unwrap()))
+            .collect()
+    }
+
+    /// Whether `candidate` is `key` shifted by some rows and columns, where
+    /// its cells are known.
+    fn is_shift_of(candidate: &[Option<char>], key: &[char]) -> bool {
+        (0..25).any(|shift| {
+            let (rows, columns) = (shift / 5, shift % 5);
+            (0..25).all(|i| {
+                let cell = (i / 5 + rows) % 5 * 5 + (i % 5 + columns) % 5;
+                candidate[i].is_none_or(|c| c == key[cell])
+            })
+        })
+    }
+
+    #[test]
+    fn reconstructed_matrices_encrypt_the_known_pair() {
+        let key = playfair::generate_key_matrix("charles dickens");
+        let config = Config::default();
+        let cipher = pipeline::encrypt(TEXT, &key, &config).cipher(&config);
+        let (dna, ambig) = playfair::split_cipher(&cipher, config.ambig_pos);
+        let reconstruction = reconstruct(TEXT, &dna, &ambig, 10).unwrap();
+        assert!(reconstruction.complete);
+        assert!(!reconstruction.candidates.is_empty());
+        assert!(reconstruction
+            .candidates
+            .iter()
+            .any(|candidate| is_shift_of(candidate, &key)));
+
+        let digraphs = align(TEXT, &dna, &ambig).unwrap();
+        for candidate in reconstruction.candidates.iter() {
+            let matrix = filled(candidate);
+            for d in digraphs.iter() {
+                let plain: String = [d.plain.0, d.plain.1].iter().collect();
+                let cipher: String = [d.cipher.0, d.cipher.1].iter().collect();
+                assert_eq!(playfair::encrypt_digraphs(&matrix, &plain), cipher);
+            }
+        }
+    }
+
+    #[test]
+    fn a_wrong_plaintext_does_not_align() {
+        let key = playfair::generate_key_matrix("charles dickens");
+        let config = Config::default();
+        let cipher = pipeline::encrypt(TEXT, &key, &config).cipher(&config);
+        let (dna, ambig) = playfair::split_cipher(&cipher, config.ambig_pos);
+        assert!(matches!(
+            reconstruct("It was the best of times", &dna, &ambig, 10),
+            Err(AlignmentError::Length { .. })
+        ));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod cryptanalysis;
+pub mod pipeline;
+pub mod playfair;
@@ -3,9 +3,19 @@
 use cli_clipboard::{ClipboardContext, ClipboardProvider};
 use eframe::egui;
 use itertools::Itertools;
-mod playfair;
+use playfair_dna::cryptanalysis::frequency::{self, Histogram};
+use playfair_dna::pipeline::defaults::{self, Defaults, Output};
+use playfair_dna::pipeline::diagnostics::Diagnostic;
+use playfair_dna::pipeline::{self, Config, Encodings, Stage, Status};
+use playfair_dna::playfair;
+use playfair_dna::playfair::kdf::KdfParams;
+use playfair_dna::playfair::mac::Tag;
+use playfair_dna::playfair::nonce;
+use playfair_dna::playfair::strength::{self, KeyStrength, Rating};
+use std::ops::Range;
 
 const WINDOW_RECT: [f32; 2] = [1024.0, 600.0];
+const MARK_COLOR: egui::Color32 = egui::Color32::from_rgb(190, 60, 50);
 
 fn main() -> Result<(), eframe::Error> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
@@ -21,7 +31,11 @@ fn main() -> Result<(), eframe::Error> {
         Box::new(|_cc| {
             // This gives us image support:
 
-            Box::<App>::default()
+            let defaults = defaults::load().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                Defaults::default()
+            });
+            Box::new(App::new(&defaults))
         }),
     )
 }
@@ -45,45 +59,19 @@ struct App {
     de_acids_after_playfair: String,
     en_ambig_vec: Vec<u8>,
     de_ambig_vec: Vec<u8>,
+    en_cipher_ambig: Vec<u8>,
+    en_tag: Option<Tag>,
+    de_status: Status,
+    de_diagnostics: Vec<Diagnostic>,
     en_ambig: String,
     de_ambig: String,
     en_dna_after_playfair: String,
     de_dna_after_playfair: String,
     en_config: Config,
     de_config: Config,
-}
-
-struct Config {
-    ambig_pos: bool,
-    text_format: Encodings,
-    // keep_whitespaces: bool,
-}
-
-#[derive(PartialEq)]
-enum Encodings {
-    UTF8,
-    UTF16,
-    //  UTF16le,
-}
-
-impl Encodings {
-    fn string(&self) -> String {
-        match self {
-            Encodings::UTF8 => "UTF-8".to_string(),
-            Encodings::UTF16 => "UTF-16 (big endian)".to_string(),
-            // Encodings::UTF16le => "UTF-16 (little endian)".to_string(),
-        }
-    }
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            ambig_pos: true,
-            text_format: Encodings::UTF8,
-            // keep_whitespaces: true,
-        }
-    }
+    en_output: Output,
+    en_kdf_cache: Option<(String, KdfParams)>,
+    de_kdf_cache: Option<(String, KdfParams)>,
 }
 
 impl Default for App {
@@ -107,121 +95,184 @@ impl Default for App {
             de_acids_after_playfair: "".to_owned(),
             en_ambig_vec: Vec::new(),
             de_ambig_vec: Vec::new(),
+            en_cipher_ambig: Vec::new(),
+            en_tag: None,
+            de_status: Status::Empty,
+            de_diagnostics: Vec::new(),
             en_ambig: "".to_owned(),
             de_ambig: "".to_owned(),
             en_dna_after_playfair: "".to_owned(),
             de_dna_after_playfair: "".to_owned(),
             en_config: Config::default(),
             de_config: Config::default(),
+            en_output: Output::default(),
+            en_kdf_cache: None,
+            de_kdf_cache: None,
         }
     }
 }
 
 impl App {
-    fn encrypt(&mut self) {
-        self.en_key_vec = playfair::generate_key_matrix(&self.en_key)
-            .iter()
-            .collect::<String>();
-        self.en_binary = "".to_string();
-
-        let bin: Vec<u8> = match self.en_config.text_format {
-            Encodings::UTF8 => self.en_plain_text.as_bytes().to_vec(),
-            Encodings::UTF16 => {
-                playfair::utf16_to_binary(&self.en_plain_text.encode_utf16().collect::<Vec<u16>>())
+    /// Settings from the config files; the decrypt side only takes what the
+    /// header does not carry. Options that do not make a pipeline are
+    /// reported like an unreadable file, and the built-in ones used instead.
+    fn new(defaults: &Defaults) -> Self {
+        let (en_config, de_config) = match (defaults.config(), defaults.pipeline().config()) {
+            (Ok(en_config), Ok(de_config)) => (en_config, de_config),
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!("{}", e);
+                Default::default()
             }
         };
-        for character in bin.iter() {
-            self.en_binary += &format!("{:b} ", character);
+        Self {
+            en_config,
+            de_config,
+            en_output: defaults.output(),
+            ..Default::default()
         }
-        self.en_dna = playfair::binary_to_dna(&playfair::utf8_to_binary(&self.en_plain_text))
-            .iter()
-            .collect();
-
-        let (acid, ambig) = playfair::dna_to_acids(&self.en_dna.chars().collect::<Vec<char>>());
-        self.en_ambig_vec = ambig;
-        self.en_acids = acid.iter().collect();
-        self.en_ambig = "".to_string();
+    }
 
-        if self.en_key_vec.len() == 25 {
-            self.en_acids_after_playfair = playfair::encrypt(
-                &self.en_key_vec.chars().collect::<Vec<char>>(),
-                &self.en_acids,
-                &mut self.en_ambig_vec,
-            );
-        } else {
-            self.en_acids_after_playfair = "".to_string();
+    fn encrypt(&mut self) {
+        // Nothing to show until the stage can encrypt, e.g. while the ADFGX
+        // keyword is being typed
+        if self.en_config.stage.error().is_some() {
+            self.en_acids_after_playfair.clear();
+            self.en_dna_after_playfair.clear();
+            self.en_cipher.clear();
+            return;
         }
+        self.en_config.renew_nonce();
+        match &self.en_config.kdf {
+            Some(params) => derive_key_vec(
+                &mut self.en_key_vec,
+                &mut self.en_kdf_cache,
+                &self.en_key,
+                params,
+            ),
+            None => {
+                self.en_key_vec = playfair::keygen::resolve_key(&self.en_key)
+                    .iter()
+                    .collect::<String>();
+                self.en_kdf_cache = None;
+            }
+        }
+        let en = pipeline::encrypt(
+            &self.en_plain_text,
+            &self.en_key_vec.chars().collect_vec(),
+            &self.en_config,
+        );
+        self.en_binary = "".to_string();
+        for character in en.binary.iter() {
+            self.en_binary += &format!("{:b} ", character);
+        }
+        self.en_dna = en.dna;
+        self.en_acids = en.acids;
+        self.en_ambig_vec = en.ambig_vec;
+        self.en_cipher_ambig = en.cipher_ambig;
+        self.en_ambig = "".to_string();
         for byte in self.en_ambig_vec.iter() {
             self.en_ambig += &format!("{}", byte);
         }
-        let acid_after_fair = self
-            .en_acids_after_playfair
-            .chars()
-            .collect::<Vec<char>>()
-            .clone();
-        self.en_dna_after_playfair = playfair::acids_to_dna(
-            &acid_after_fair,
-            &vec![0u8; self.en_acids_after_playfair.len()],
-        )
-        .iter()
-        .collect();
+        self.en_acids_after_playfair = en.acids_after_playfair;
+        self.en_dna_after_playfair = en.dna_after_playfair;
+        self.en_tag = en.tag;
 
-        self.en_cipher = playfair::dna_plus_ambig(
-            &self.en_dna_after_playfair,
-            &self.en_ambig_vec,
-            self.en_config.ambig_pos,
+        self.assemble_cipher();
+    }
+
+    fn assemble_cipher(&mut self) {
+        let cipher = playfair::with_tag(
+            &playfair::with_header(
+                &self.en_config.header(),
+                &playfair::dna_plus_ambig(
+                    &self.en_dna_after_playfair,
+                    &self.en_cipher_ambig,
+                    self.en_config.ambig_pos,
+                ),
+            ),
+            self.en_tag.as_ref(),
         );
+        self.en_cipher = self
+            .en_output
+            .render(&cipher, "message")
+            .trim_end()
+            .to_string();
     }
 
     fn decrypt(&mut self) {
-        self.de_key_vec = playfair::generate_key_matrix(&self.de_key)
-            .iter()
-            .collect::<String>();
-        let (dna, ambig) = playfair::split_cipher(&self.de_cipher, self.de_config.ambig_pos);
-        self.de_dna = dna.iter().collect();
-        self.de_ambig_vec = ambig;
-        self.de_ambig = "".to_string();
-        if self.de_dna.len() != self.de_ambig_vec.len() * 3 {
-            println!("something went wrong, dna/ambig length mismatch");
+        if self.de_config.stage.error().is_some() {
+            self.de_acids_after_playfair.clear();
+            self.de_dna_after_playfair.clear();
+            self.de_plain_text.clear();
+            self.de_status = Status::Empty;
+            self.de_diagnostics.clear();
             return;
         }
+        let cipher = defaults::strip_fasta(&self.de_cipher);
+        let header = playfair::cipher_header(&cipher).unwrap_or_default();
+        match &header.kdf {
+            Some(params) => derive_key_vec(
+                &mut self.de_key_vec,
+                &mut self.de_kdf_cache,
+                &self.de_key,
+                params,
+            ),
+            None => {
+                self.de_key_vec = playfair::keygen::resolve_key(&self.de_key)
+                    .iter()
+                    .collect::<String>();
+                self.de_kdf_cache = None;
+            }
+        }
+        let de = pipeline::decrypt(
+            &cipher,
+            &self.de_key_vec.chars().collect_vec(),
+            &self.de_config,
+        );
+        self.de_dna = de.dna;
+        self.de_ambig_vec = de.ambig_vec;
+        self.de_ambig = "".to_string();
         for byte in self.de_ambig_vec.iter() {
             self.de_ambig += &format!("{}", byte);
         }
-        let (acids, _) = playfair::dna_to_acids(&self.de_dna.chars().collect_vec());
-        if acids.len() != self.de_ambig_vec.len() {
-            println!("something went wrong, acids/ambig length mismatch");
-            return;
-        }
-        self.de_acids = acids.iter().collect();
-        let unsanitized_acids = playfair::decrypt(&self.de_key_vec, &self.de_acids);
-        self.de_acids_after_playfair =
-            playfair::sanitize_acids(&unsanitized_acids, &self.de_ambig_vec);
-        let mut sanitized_ambig_vector = self.de_ambig_vec.clone();
-        playfair::sanitize_ambig(&mut sanitized_ambig_vector);
-        self.de_dna_after_playfair = playfair::acids_to_dna(
-            &self.de_acids_after_playfair.chars().collect::<Vec<char>>(),
-            &sanitized_ambig_vector,
-        )
-        .iter()
-        .collect();
-
-        let bin = playfair::dna_to_binary(&self.de_dna_after_playfair);
+        self.de_acids = de.acids;
+        self.de_acids_after_playfair = de.acids_after_playfair;
+        self.de_dna_after_playfair = de.dna_after_playfair;
         self.de_binary = "".to_string();
-        for character in bin.iter() {
+        for character in de.binary.iter() {
             self.de_binary += &format!("{:b} ", character);
         }
-        self.de_plain_text = match String::from_utf8(bin) {
-            Ok(v) => v,
-            Err(e) => String::from(format!(
-                "wrong binary format - check your key! \n err: {}",
-                e
-            )),
-        };
+        self.de_plain_text = de.plain_text;
+        self.de_status = de.status;
+        self.de_diagnostics = de.diagnostics;
+    }
+}
+
+// Argon2 is too slow to rerun on every keystroke in the text fields, so the
+// matrix is only derived again when the passphrase or parameters change
+fn derive_key_vec(
+    key_vec: &mut String,
+    cache: &mut Option<(String, KdfParams)>,
+    passphrase: &str,
+    params: &KdfParams,
+) {
+    let input = (passphrase.to_string(), params.clone());
+    if cache.as_ref() == Some(&input) {
+        return;
     }
+    *key_vec = match playfair::kdf::derive_key_matrix(passphrase, params) {
+        Ok(key) => key.iter().collect(),
+        Err(e) => {
+            eprintln!("key derivation error: {}", e);
+            String::new()
+        }
+    };
+    *cache = Some(input);
 }
 
 const TEXT_AREA_SIZE: egui::Vec2 = egui::vec2(WINDOW_RECT[0] / 2.5, 64.0);
+const KEY_BUTTON_WIDTH: f32 = 90.0;
+const DEFAULT_PERIOD: usize = 5;
 const LAYOUT: egui::Layout = egui::Layout {
     main_dir: egui::Direction::TopDown,
     main_wrap: false,
@@ -260,6 +311,225 @@ const SEPARATOR_UI_RECT: egui::Rect = egui::Rect {
     },
 };
 
+fn stage_controls(ui: &mut egui::Ui, config: &mut Config, id: &str) -> bool {
+    let before = (config.stage.clone(), config.rounds);
+    let stage = &mut config.stage;
+    let period = stage.period().unwrap_or(DEFAULT_PERIOD);
+    let keyword = match stage {
+        Stage::Adfgx(keyword) => keyword.clone(),
+        _ => String::new(),
+    };
+    ui.label("Stage: ");
+    ui.horizontal(|ui| {
+        ui.add_space(60.0);
+        egui::ComboBox::from_id_source(id)
+            .width(150.0)
+            .selected_text(stage.string())
+            .show_ui(ui, |ui| {
+                for option in [
+                    Stage::Playfair,
+                    Stage::Bifid(period),
+                    Stage::PlayfairBifid(period),
+                    Stage::Trifid(period),
+                    Stage::Adfgx(keyword),
+                ] {
+                    let label = option.string();
+                    ui.selectable_value(stage, option, label);
+                }
+            });
+        if let Some(mut period) = stage.period() {
+            ui.add_space(10.0);
+            ui.label("Period: ");
+            if ui
+                .add(egui::DragValue::new(&mut period).clamp_range(0..=64))
+                .changed()
+            {
+                *stage = stage.with_period(period);
+            }
+        }
+        if let Stage::Adfgx(keyword) = stage {
+            ui.add_space(10.0);
+            ui.add(
+                egui::TextEdit::singleline(keyword)
+                    .desired_width(100.0)
+                    .hint_text("Transposition key"),
+            );
+        }
+        if let Some(error) = stage.error() {
+            ui.colored_label(MARK_COLOR, "!").on_hover_text(error);
+        }
+    });
+    ui.label("Rounds: ");
+    ui.horizontal(|ui| {
+        ui.add_space(60.0);
+        ui.add(egui::DragValue::new(&mut config.rounds).clamp_range(1..=16));
+    });
+    (config.stage.clone(), config.rounds) != before
+}
+
+fn strength_meter(ui: &mut egui::Ui, strength: &KeyStrength, offset: f32) {
+    let color = match strength.rating() {
+        Rating::Weak => egui::Color32::from_rgb(190, 60, 50),
+        Rating::Fair => egui::Color32::from_rgb(200, 150, 40),
+        Rating::Strong => egui::Color32::from_rgb(60, 150, 70),
+    };
+    let bar = ui
+        .horizontal(|ui| {
+            ui.add_space(offset);
+            ui.add(
+                egui::ProgressBar::new(
+                    (strength.entropy_bits / strength::max_entropy_bits()) as f32,
+                )
+                .desired_width(TEXT_AREA_SIZE.x)
+                .fill(color)
+                .text(format!(
+                    "{} - {:.0} bits, {} of 25 cells from the key",
+                    strength.rating().string(),
+                    strength.entropy_bits,
+                    strength.keyword_cells
+                )),
+            )
+        })
+        .inner;
+    let warnings = strength.warnings();
+    if bar.hovered() && !warnings.is_empty() {
+        egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("strength_tooltip"), |ui| {
+            for warning in warnings.iter() {
+                ui.label(warning);
+            }
+        });
+    }
+}
+
+const HISTOGRAM_HEIGHT: f32 = 70.0;
+const PLAIN_COLOR: egui::Color32 = egui::Color32::from_rgb(70, 130, 180);
+const CIPHER_COLOR: egui::Color32 = egui::Color32::from_rgb(200, 110, 60);
+
+// Bar chart of one or more histograms over the same labels, drawn side by
+// side per label. Labels are only printed when there is room for them.
+fn histogram(ui: &mut egui::Ui, name: &str, series: &[(&Histogram, egui::Color32)], offset: f32) {
+    ui.label(name);
+    let labels = &series[0].0.labels;
+    let max = series
+        .iter()
+        .map(|(h, _)| h.max())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let show_labels = labels.len() <= 25;
+    let height = HISTOGRAM_HEIGHT + if show_labels { 12.0 } else { 0.0 };
+    ui.horizontal(|ui| {
+        ui.add_space(offset);
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(TEXT_AREA_SIZE.x, height), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        let slot = rect.width() / labels.len().max(1) as f32;
+        let bar = (slot - 1.0) / series.len() as f32;
+        for (i, label) in labels.iter().enumerate() {
+            let x = rect.min.x + i as f32 * slot;
+            for (j, (h, color)) in series.iter().enumerate() {
+                let count = h.counts.get(i).copied().unwrap_or(0);
+                let top = rect.min.y + HISTOGRAM_HEIGHT * (1.0 - count as f32 / max as f32);
+                painter.rect_filled(
+                    egui::Rect::from_min_max(
+                        egui::pos2(x + j as f32 * bar, top),
+                        egui::pos2(x + (j + 1) as f32 * bar, rect.min.y + HISTOGRAM_HEIGHT),
+                    ),
+                    0.0,
+                    *color,
+                );
+            }
+            if show_labels {
+                painter.text(
+                    egui::pos2(x + slot / 2.0, rect.max.y),
+                    egui::Align2::CENTER_BOTTOM,
+                    label,
+                    egui::FontId::monospace(8.0),
+                    ui.visuals().text_color(),
+                );
+            }
+        }
+        if let Some(pointer) = response.hover_pos() {
+            let i = ((pointer.x - rect.min.x) / slot) as usize;
+            if let Some(label) = labels.get(i) {
+                let counts = series
+                    .iter()
+                    .map(|(h, _)| h.counts.get(i).copied().unwrap_or(0).to_string())
+                    .join(" / ");
+                egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("histogram_tooltip"), |ui| {
+                    ui.label(format!("{}: {}", label, counts));
+                });
+            }
+        }
+    });
+}
+
+fn analysis_panel(ui: &mut egui::Ui, dna: &str, acids: &str, encrypted: &str, offset: f32) {
+    let plain_acids = frequency::acids(acids);
+    let encrypted_acids = frequency::acids(encrypted);
+    ui.label(format!(
+        "GC content: {:.1} %    IoC plain: {:.4}    IoC encrypted: {:.4}",
+        frequency::gc_content(dna) * 100.0,
+        plain_acids.index_of_coincidence(),
+        encrypted_acids.index_of_coincidence()
+    ));
+    histogram(
+        ui,
+        "Nucleotides",
+        &[(&frequency::nucleotides(dna), PLAIN_COLOR)],
+        offset,
+    );
+    histogram(
+        ui,
+        "Codons",
+        &[(&frequency::codons(dna), PLAIN_COLOR)],
+        offset,
+    );
+    histogram(
+        ui,
+        "Acids (plain / encrypted)",
+        &[
+            (&plain_acids, PLAIN_COLOR),
+            (&encrypted_acids, CIPHER_COLOR),
+        ],
+        offset,
+    );
+    histogram(
+        ui,
+        "Top digraphs (plain)",
+        &[(&frequency::digraphs(acids, 20), PLAIN_COLOR)],
+        offset,
+    );
+    histogram(
+        ui,
+        "Top digraphs (encrypted)",
+        &[(&frequency::digraphs(encrypted, 20), CIPHER_COLOR)],
+        offset,
+    );
+}
+
+/// The cipher text with the regions named by the decrypt diagnostics marked.
+fn highlighted(ui: &egui::Ui, text: &str, marked: &[Range<usize>]) -> egui::text::LayoutJob {
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let color = ui.visuals().text_color();
+    let mut job = egui::text::LayoutJob::default();
+    let chars: Vec<char> = text.chars().collect();
+    for (is_marked, run) in &chars
+        .iter()
+        .enumerate()
+        .group_by(|(i, _)| marked.iter().any(|r| r.contains(i)))
+    {
+        let run: String = run.map(|(_, c)| *c).collect();
+        let mut format = egui::TextFormat::simple(font_id.clone(), color);
+        if is_marked {
+            format.background = MARK_COLOR.gamma_multiply(0.35);
+        }
+        job.append(&run, 0.0, format);
+    }
+    job
+}
+
 fn create_input_box(ui: &mut egui::Ui, value: &mut String, name: &str, id: &str, offset: f32) {
     ui.vertical_centered(|ui| {
         let link = ui.link(format!("{}:", name));
@@ -321,11 +591,7 @@ impl eframe::App for App {
                                         .clicked()
                                     {
                                         self.en_config.ambig_pos = true;
-                                        self.en_cipher = playfair::dna_plus_ambig(
-                                            &self.en_dna_after_playfair,
-                                            &self.en_ambig_vec,
-                                            self.en_config.ambig_pos,
-                                        );
+                                        self.assemble_cipher();
                                     }
                                     _ui_l.add_space(10.0);
                                     if _ui_l
@@ -336,11 +602,15 @@ impl eframe::App for App {
                                         .clicked()
                                     {
                                         self.en_config.ambig_pos = false;
-                                        self.en_cipher = playfair::dna_plus_ambig(
-                                            &self.en_dna_after_playfair,
-                                            &self.en_ambig_vec,
-                                            self.en_config.ambig_pos,
-                                        );
+                                        self.assemble_cipher();
+                                    }
+                                    _ui_l.add_space(10.0);
+                                    if _ui_l
+                                        .checkbox(&mut self.en_config.mask_ambig, "Encrypted")
+                                        .on_hover_text("mask the ambiguity digits with a key- and nonce-derived keystream")
+                                        .changed()
+                                    {
+                                        self.encrypt();
                                     }
                                 });
                                 _ui_l.label("Encoding: ");
@@ -361,17 +631,108 @@ impl eframe::App for App {
                                                 "UTF-16 (big endian)",
                                             );
                                         });
+                                });
+                                if stage_controls(_ui_l, &mut self.en_config, "stages") {
+                                    self.encrypt();
+                                }
+                                _ui_l.label("Key: ");
+                                _ui_l.horizontal(|_ui_l| {
+                                    _ui_l.add_space(60.0);
+                                    if _ui_l
+                                        .add(egui::RadioButton::new(
+                                            self.en_config.kdf.is_none(),
+                                            "Keyword",
+                                        ))
+                                        .clicked()
+                                    {
+                                        self.en_config.kdf = None;
+                                        self.encrypt();
+                                    }
+                                    _ui_l.add_space(10.0);
+                                    if _ui_l
+                                        .add(egui::RadioButton::new(
+                                            self.en_config.kdf.is_some(),
+                                            "Passphrase (Argon2)",
+                                        ))
+                                        .clicked()
+                                        && self.en_config.kdf.is_none()
+                                    {
+                                        self.en_config.kdf = Some(KdfParams::random());
+                                        self.encrypt();
+                                    }
+                                });
+                                _ui_l.label("Nonce: ");
+                                _ui_l.horizontal(|_ui_l| {
+                                    _ui_l.add_space(60.0);
+                                    let mut enabled = self.en_config.nonce.is_some();
+                                    if _ui_l
+                                        .add_enabled(
+                                            !self.en_config.mask_ambig,
+                                            egui::Checkbox::new(&mut enabled, "Random per message"),
+                                        )
+                                        .on_hover_text(
+                                            "identical messages give different ciphers, \
+                                             older versions cannot decrypt them",
+                                        )
+                                        .on_disabled_hover_text(
+                                            "an encrypted ambiguity stream always takes a nonce",
+                                        )
+                                        .changed()
+                                    {
+                                        self.en_config.nonce = enabled.then(nonce::random);
+                                        self.encrypt();
+                                    }
+                                });
+                                _ui_l.label("Tag: ");
+                                _ui_l.horizontal(|_ui_l| {
+                                    _ui_l.add_space(60.0);
+                                    if _ui_l
+                                        .checkbox(&mut self.en_config.mac, "Authentication tag")
+                                        .on_hover_text(
+                                            "append an HMAC-SHA256 tag so that wrong keys \
+                                             and modified ciphers are detected",
+                                        )
+                                        .changed()
+                                    {
+                                        self.encrypt();
+                                    }
                                 })
                             });
                         _ui_l.add_space(10.0);
-                        let key = _ui_l.add(
-                            egui::TextEdit::singleline(&mut self.en_key)
-                                .min_size(egui::vec2(TEXT_AREA_SIZE.x / 2.0, 20.0))
-                                .hint_text("Key"),
-                        );
-                        if key.changed() && !self.en_plain_text.is_empty() {
+                        let (key, generate) = _ui_l
+                            .horizontal(|_ui_l| {
+                                _ui_l.add_space(l_ui_offset);
+                                let key = _ui_l.add(
+                                    egui::TextEdit::singleline(&mut self.en_key)
+                                        .min_size(egui::vec2(TEXT_AREA_SIZE.x / 2.0, 20.0))
+                                        .desired_width(TEXT_AREA_SIZE.x - KEY_BUTTON_WIDTH - 8.0)
+                                        .hint_text("Key"),
+                                );
+                                _ui_l.add_space(8.0);
+                                let generate = _ui_l.add(
+                                    egui::Button::new("Generate key")
+                                        .min_size(egui::vec2(KEY_BUTTON_WIDTH, 20.0)),
+                                );
+                                (key, generate)
+                            })
+                            .inner;
+                        if generate.clicked() {
+                            self.en_key = playfair::keygen::export_key(
+                                &playfair::keygen::random_key_matrix(),
+                            )
+                            .unwrap();
+                        }
+                        if (key.changed() || generate.clicked()) && !self.en_plain_text.is_empty() {
                             self.encrypt();
                         }
+                        let strength = match self.en_config.kdf {
+                            Some(_) => strength::analyze_passphrase(
+                                &self.en_key,
+                                &self.en_key_vec.chars().collect_vec(),
+                            ),
+                            None => strength::analyze_key(&self.en_key),
+                        };
+                        strength_meter(_ui_l, &strength, l_ui_offset);
                         if key.hovered() && !self.en_key_vec.is_empty() {
                             //TODO show key matrix
                             egui::show_tooltip_at_pointer(
@@ -473,6 +834,19 @@ impl eframe::App for App {
                                 );
                             });
                         });
+                        egui::CollapsingHeader::new("Analysis")
+                            .id_source("cipher_analysis")
+                            .show_unindented(_ui_l, |_ui_l| {
+                                _ui_l.vertical_centered(|_ui_l| {
+                                    analysis_panel(
+                                        _ui_l,
+                                        &self.en_dna,
+                                        &self.en_acids,
+                                        &self.en_acids_after_playfair,
+                                        l_ui_offset,
+                                    );
+                                });
+                            });
 
                         _ui_l.add_space(10.0);
                     });
@@ -539,17 +913,36 @@ impl eframe::App for App {
                                                 "UTF-16 (big endian)",
                                             );
                                         });
-                                })
+                                });
+                                if stage_controls(_ui_r, &mut self.de_config, "de_stages") {
+                                    self.decrypt();
+                                }
                             });
                         _ui_r.add_space(10.0);
-                        let key = _ui_r.add(
-                            egui::TextEdit::singleline(&mut self.de_key)
-                                .min_size(egui::vec2(TEXT_AREA_SIZE.x / 2.0, 20.0))
-                                .hint_text("Key"),
-                        );
+                        let key = _ui_r
+                            .horizontal(|_ui_r| {
+                                _ui_r.add_space(r_ui_offset);
+                                _ui_r.add(
+                                    egui::TextEdit::singleline(&mut self.de_key)
+                                        .min_size(egui::vec2(TEXT_AREA_SIZE.x / 2.0, 20.0))
+                                        .desired_width(TEXT_AREA_SIZE.x)
+                                        .hint_text("Key"),
+                                )
+                            })
+                            .inner;
                         if key.changed() && !self.de_cipher.is_empty() {
                             self.decrypt();
                         }
+                        let strength = match playfair::cipher_header(&self.de_cipher)
+                            .and_then(|header| header.kdf)
+                        {
+                            Some(_) => strength::analyze_passphrase(
+                                &self.de_key,
+                                &self.de_key_vec.chars().collect_vec(),
+                            ),
+                            None => strength::analyze_key(&self.de_key),
+                        };
+                        strength_meter(_ui_r, &strength, r_ui_offset);
                         if key.hovered() && !self.de_key_vec.is_empty() {
                             //TODO show key matrix
                             egui::show_tooltip_at_pointer(
@@ -586,10 +979,22 @@ impl eframe::App for App {
                                 .max_width(TEXT_AREA_SIZE.x)
                                 .max_height(TEXT_AREA_SIZE.y)
                                 .show(_ui_r, |_ui_r| {
+                                    let marked: Vec<Range<usize>> = self
+                                        .de_diagnostics
+                                        .iter()
+                                        .flat_map(|d| d.offsets.clone())
+                                        .collect();
+                                    let mut layouter =
+                                        |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                                            let mut job = highlighted(ui, text, &marked);
+                                            job.wrap.max_width = wrap_width;
+                                            ui.fonts(|f| f.layout_job(job))
+                                        };
                                     let text_area = _ui_r.add(
                                         egui::TextEdit::multiline(&mut self.de_cipher)
                                             .min_size(TEXT_AREA_SIZE)
-                                            .hint_text("Cipher (encrypted DNA + Ambig)"),
+                                            .hint_text("Cipher (encrypted DNA + Ambig)")
+                                            .layouter(&mut layouter),
                                     );
                                     if text_area.changed() {
                                         self.decrypt();
@@ -597,6 +1002,24 @@ impl eframe::App for App {
                                 });
                         });
                         _ui_r.add_space(10.0);
+                        if self.de_status != Status::Empty {
+                            let color = match self.de_status {
+                                Status::Verified => egui::Color32::from_rgb(60, 150, 70),
+                                Status::Decrypted => _ui_r.visuals().text_color(),
+                                _ => egui::Color32::from_rgb(190, 60, 50),
+                            };
+                            _ui_r.colored_label(color, self.de_status.string());
+                        }
+                        for diagnostic in self.de_diagnostics.iter() {
+                            let causes: Vec<&str> =
+                                diagnostic.causes.iter().map(|c| c.string()).collect();
+                            _ui_r
+                                .colored_label(
+                                    MARK_COLOR,
+                                    format!("{}: {}", diagnostic.stage.string(), diagnostic.message),
+                                )
+                                .on_hover_text(format!("likely: {}", causes.join(", ")));
+                        }
                         create_input_box(
                             _ui_r,
                             &mut self.de_plain_text,
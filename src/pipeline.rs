@@ -0,0 +1,630 @@
+use crate::playfair::{
+    self, adfgx, fractionation,
+    header::Header,
+    kdf::KdfParams,
+    mac, mask,
+    nonce::{self, Nonce},
+    CodonTable,
+};
+use diagnostics::{Cause, CipherMap, DecryptStage, Diagnostic};
+use itertools::Itertools;
+
+pub mod defaults;
+pub mod diagnostics;
+pub mod rounds;
+pub mod selftest;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encodings {
+    UTF8,
+    UTF16,
+    //  UTF16le,
+}
+
+impl Encodings {
+    pub fn string(&self) -> String {
+        match self {
+            Encodings::UTF8 => "UTF-8".to_string(),
+            Encodings::UTF16 => "UTF-16 (big endian)".to_string(),
+            // Encodings::UTF16le => "UTF-16 (little endian)".to_string(),
+        }
+    }
+}
+
+/// Substitution applied to the acid stream. Fractionating stages carry their
+/// period, ADFGX its transposition keyword.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stage {
+    Playfair,
+    Bifid(usize),
+    PlayfairBifid(usize),
+    Trifid(usize),
+    Adfgx(String),
+}
+
+impl Stage {
+    pub fn string(&self) -> String {
+        match self {
+            Stage::Playfair => "Playfair".to_string(),
+            Stage::Bifid(_) => "Bifid".to_string(),
+            Stage::PlayfairBifid(_) => "Playfair + Bifid".to_string(),
+            Stage::Trifid(_) => "Trifid".to_string(),
+            Stage::Adfgx(_) => "ADFGX".to_string(),
+        }
+    }
+
+    pub fn period(&self) -> Option<usize> {
+        match self {
+            Stage::Playfair | Stage::Adfgx(_) => None,
+            Stage::Bifid(p) | Stage::PlayfairBifid(p) | Stage::Trifid(p) => Some(*p),
+        }
+    }
+
+    pub fn with_period(&self, period: usize) -> Stage {
+        match self {
+            Stage::Bifid(_) => Stage::Bifid(period),
+            Stage::PlayfairBifid(_) => Stage::PlayfairBifid(period),
+            Stage::Trifid(_) => Stage::Trifid(period),
+            other => other.clone(),
+        }
+    }
+
+    /// Why the stage cannot encrypt as configured, if it cannot.
+    pub fn error(&self) -> Option<&'static str> {
+        match self {
+            Stage::Adfgx(keyword) if !adfgx::valid_keyword(keyword) => Some(adfgx::KEYWORD_ERROR),
+            _ => None,
+        }
+    }
+
+    pub fn codon_table(&self) -> CodonTable {
+        match self {
+            Stage::Trifid(_) => CodonTable::Trifid,
+            _ => CodonTable::Standard,
+        }
+    }
+
+    /// Encrypts an acid string; Playfair fillers are recorded in `ambig_vec` as 4s.
+    pub fn encrypt(&self, key: &[char], acids: &str, ambig_vec: &mut Vec<u8>) -> String {
+        match self {
+            Stage::Playfair => playfair::encrypt(key, acids, ambig_vec),
+            Stage::Bifid(p) => fractionation::bifid_encrypt(key, acids, *p),
+            Stage::PlayfairBifid(p) => {
+                fractionation::bifid_encrypt(key, &playfair::encrypt(key, acids, ambig_vec), *p)
+            }
+            Stage::Trifid(p) => fractionation::trifid_encrypt(key, acids, *p),
+            Stage::Adfgx(keyword) => adfgx::encrypt(key, acids, keyword),
+        }
+    }
+
+    /// `encrypt` for rounds after the first: Playfair pairs are encrypted as
+    /// they come, without fillers, so the length is preserved.
+    pub fn encrypt_round(&self, key: &[char], acids: &str) -> String {
+        match self {
+            Stage::Playfair => playfair::encrypt_digraphs(key, acids),
+            Stage::PlayfairBifid(p) => {
+                fractionation::bifid_encrypt(key, &playfair::encrypt_digraphs(key, acids), *p)
+            }
+            other => other.encrypt(key, acids, &mut Vec::new()),
+        }
+    }
+
+    /// Inverse of `encrypt`, fillers are left in place for `sanitize_acids`.
+    pub fn decrypt(&self, key: &[char], acids: &str) -> String {
+        let key_string = key.iter().collect::<String>();
+        match self {
+            Stage::Playfair => playfair::decrypt(&key_string, acids),
+            Stage::Bifid(p) => fractionation::bifid_decrypt(key, acids, *p),
+            Stage::PlayfairBifid(p) => {
+                playfair::decrypt(&key_string, &fractionation::bifid_decrypt(key, acids, *p))
+            }
+            Stage::Trifid(p) => fractionation::trifid_decrypt(key, acids, *p),
+            Stage::Adfgx(keyword) => adfgx::decrypt(key, acids, keyword),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub ambig_pos: bool,
+    pub text_format: Encodings,
+    pub kdf: Option<KdfParams>,
+    pub stage: Stage,
+    pub rounds: usize,
+    /// Needs a nonce, or every message under one key gets the same mask.
+    pub mask_ambig: bool,
+    /// Set to a fresh `nonce::random()` for every message; `None` keeps the
+    /// cipher deterministic and compatible with headerless ciphers.
+    pub nonce: Option<Nonce>,
+    pub mac: bool,
+    // keep_whitespaces: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ambig_pos: true,
+            text_format: Encodings::UTF8,
+            kdf: None,
+            stage: Stage::Playfair,
+            rounds: 1,
+            mask_ambig: false,
+            nonce: None,
+            mac: false,
+            // keep_whitespaces: true,
+        }
+    }
+}
+
+impl Config {
+    /// A fresh nonce for the next message if it takes one: when enabled, and
+    /// always with a masked ambiguity stream.
+    pub fn renew_nonce(&mut self) {
+        if self.nonce.is_some() || self.mask_ambig {
+            self.nonce = Some(nonce::random());
+        }
+    }
+
+    pub fn header(&self) -> Header {
+        Header {
+            kdf: self.kdf.clone(),
+            mask_ambig: self.mask_ambig,
+            nonce: self.nonce,
+            mac: self.mac,
+        }
+    }
+
+    /// Bytes the authentication tag covers besides the plaintext: the header
+    /// as sent and every setting the decrypt side has to match.
+    fn authenticated(&self, header: &Header) -> Vec<u8> {
+        let stage = match &self.stage {
+            Stage::Adfgx(keyword) => format!("{}:{}", self.stage.string(), keyword),
+            stage => format!("{}:{}", stage.string(), stage.period().unwrap_or(0)),
+        };
+        let mut bytes = header.to_bytes();
+        bytes.extend_from_slice(
+            format!(
+                "{};{};{};{}",
+                self.ambig_pos,
+                self.text_format.string(),
+                stage,
+                self.rounds
+            )
+            .as_bytes(),
+        );
+        bytes
+    }
+}
+
+/// Every intermediate stage of an encryption, as shown in the GUI.
+#[derive(Debug, Default)]
+pub struct Encrypted {
+    pub binary: Vec<u8>,
+    pub dna: String,
+    pub acids: String,
+    pub ambig_vec: Vec<u8>,
+    /// The ambiguity stream as it goes into the cipher, masked if configured.
+    pub cipher_ambig: Vec<u8>,
+    pub acids_after_playfair: String,
+    pub dna_after_playfair: String,
+    pub tag: Option<mac::Tag>,
+}
+
+impl Encrypted {
+    pub fn cipher(&self, config: &Config) -> String {
+        playfair::with_tag(
+            &playfair::with_header(
+                &config.header(),
+                &playfair::dna_plus_ambig(
+                    &self.dna_after_playfair,
+                    &self.cipher_ambig,
+                    config.ambig_pos,
+                ),
+            ),
+            self.tag.as_ref(),
+        )
+    }
+
+    /// The cipher as two streams: the DNA alone, and the header, ambiguity
+    /// stream and tag. `playfair::join_raw` puts them back together.
+    pub fn raw(&self, config: &Config) -> (String, String) {
+        (
+            self.dna_after_playfair.clone(),
+            playfair::raw_ambig(&config.header(), &self.cipher_ambig, self.tag.as_ref()),
+        )
+    }
+}
+
+/// The matrix a message is actually encrypted with.
+fn message_key(master: &[char], nonce: Option<&Nonce>) -> Vec<char> {
+    match nonce {
+        Some(nonce) if master.len() == 25 => nonce::message_key(master, nonce),
+        _ => master.to_vec(),
+    }
+}
+
+pub fn encrypt(text: &str, key: &[char], config: &Config) -> Encrypted {
+    let key = &message_key(key, config.nonce.as_ref());
+    let mut en = Encrypted {
+        binary: match config.text_format {
+            Encodings::UTF8 => playfair::utf8_to_binary(text),
+            Encodings::UTF16 => {
+                playfair::utf16_to_binary(&text.encode_utf16().collect::<Vec<u16>>())
+            }
+        },
+        ..Default::default()
+    };
+    en.dna = playfair::binary_to_dna(&en.binary).iter().collect();
+
+    let table = config.stage.codon_table();
+    let (acid, ambig) = playfair::dna_to_acids_with(&en.dna.chars().collect_vec(), table);
+    en.ambig_vec = ambig;
+    en.acids = acid.iter().collect();
+
+    if key.len() == 25 {
+        en.acids_after_playfair = rounds::encrypt(
+            &config.stage,
+            key,
+            &en.acids,
+            &mut en.ambig_vec,
+            config.rounds,
+        );
+    }
+    en.cipher_ambig = match config.mask_ambig {
+        true => mask::mask(key, config.nonce.as_ref(), &en.ambig_vec),
+        false => en.ambig_vec.clone(),
+    };
+    en.dna_after_playfair = playfair::acids_to_dna_with(
+        &en.acids_after_playfair.chars().collect_vec(),
+        &vec![0u8; en.acids_after_playfair.len()],
+        table,
+    )
+    .iter()
+    .collect();
+    if config.mac && key.len() == 25 {
+        en.tag = Some(mac::tag(
+            key,
+            &config.authenticated(&config.header()),
+            &playfair::dna_to_binary(&en.dna),
+        ));
+    }
+    en
+}
+
+/// Outcome of a decryption, for the status line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Status {
+    #[default]
+    Empty,
+    /// The tag matched: right key, unmodified cipher and settings.
+    Verified,
+    /// No tag to check, but the result is valid text.
+    Decrypted,
+    /// The tag did not match: wrong key, wrong settings or a modified cipher.
+    BadTag,
+    /// No tag to check and the result is not valid text, most likely a wrong key.
+    InvalidText,
+    Malformed(String),
+}
+
+impl Status {
+    pub fn string(&self) -> String {
+        match self {
+            Status::Empty => String::new(),
+            Status::Verified => "decrypted, tag verified".to_string(),
+            Status::Decrypted => "decrypted, no tag to verify".to_string(),
+            Status::BadTag => "tag mismatch - wrong key or modified cipher".to_string(),
+            Status::InvalidText => "no valid text - check your key".to_string(),
+            Status::Malformed(reason) => format!("malformed cipher: {}", reason),
+        }
+    }
+}
+
+/// Every intermediate stage of a decryption. Stages after a failure stay empty.
+#[derive(Debug, Default)]
+pub struct Decrypted {
+    pub dna: String,
+    pub ambig_vec: Vec<u8>,
+    pub acids: String,
+    pub acids_after_playfair: String,
+    pub dna_after_playfair: String,
+    pub binary: Vec<u8>,
+    pub plain_text: String,
+    pub status: Status,
+    /// What went wrong and where, in pipeline order.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Decrypted {
+    fn fail(mut self, diagnostic: Diagnostic) -> Self {
+        self.status = Status::Malformed(diagnostic.message.clone());
+        self.diagnostics.push(diagnostic);
+        self
+    }
+}
+
+/// Char ranges of at most `MAX_MARKED` acids, so a wrong key does not paint
+/// the whole cipher.
+const MAX_MARKED: usize = 32;
+
+fn mark_acids(map: &CipherMap, acids: &[usize], with_codon: bool) -> Vec<std::ops::Range<usize>> {
+    acids
+        .iter()
+        .take(MAX_MARKED)
+        .flat_map(|&i| map.acid(i, with_codon))
+        .collect()
+}
+
+/// The text of a decrypted binary without its padding, or the number of
+/// valid bytes and the error.
+fn decode(binary: &[u8], format: Encodings) -> Result<String, (usize, String)> {
+    match format {
+        Encodings::UTF8 => std::str::from_utf8(playfair::strip_padding(binary))
+            .map(|text| text.to_string())
+            .map_err(|e| (e.valid_up_to(), e.to_string())),
+        Encodings::UTF16 => {
+            let units = playfair::binary_to_utf16(binary);
+            let mut text = String::new();
+            for c in char::decode_utf16(playfair::strip_padding(&units).iter().copied()) {
+                match c {
+                    Ok(c) => text.push(c),
+                    Err(e) => return Err((text.encode_utf16().count() * 2, e.to_string())),
+                }
+            }
+            match binary.len() % 2 {
+                0 => Ok(text),
+                _ => Err((binary.len() - 1, "odd number of UTF-16 bytes".to_string())),
+            }
+        }
+    }
+}
+
+pub fn decrypt(cipher: &str, key: &[char], config: &Config) -> Decrypted {
+    let mut de = Decrypted::default();
+    let map = CipherMap::new(cipher, config.ambig_pos);
+    if key.len() != 25 || map.is_empty() {
+        return de;
+    }
+    let ignored = map.ignored();
+    if !ignored.is_empty() {
+        de.diagnostics.push(Diagnostic::new(
+            DecryptStage::Split,
+            format!(
+                "ignored {} characters that are not nucleotides",
+                ignored.iter().map(|r| r.len()).sum::<usize>()
+            ),
+            ignored,
+            &[Cause::Corrupted],
+        ));
+    }
+    let sections = map.section_count();
+    if !(2..=4).contains(&sections) {
+        return de.fail(Diagnostic::new(
+            DecryptStage::Split,
+            format!(
+                "expected 2 to 4 sections separated by '-', found {}",
+                sections
+            ),
+            (0..sections).flat_map(|i| map.section(i)).collect(),
+            match sections < 2 {
+                true => &[Cause::Truncated, Cause::Corrupted],
+                false => &[Cause::Corrupted],
+            },
+        ));
+    }
+    let header = match playfair::cipher_header(cipher) {
+        Some(header) => header,
+        None if sections > 2 => {
+            return de.fail(Diagnostic::new(
+                DecryptStage::Split,
+                "unreadable header",
+                map.section(0),
+                &[Cause::Corrupted, Cause::Truncated],
+            ))
+        }
+        None => Default::default(),
+    };
+    let tag = playfair::cipher_tag(cipher);
+    if header.mac != (sections == 4) || (header.mac && tag.is_none()) {
+        return de.fail(Diagnostic::new(
+            DecryptStage::Split,
+            "missing or unreadable tag",
+            map.section(sections - 1),
+            &[Cause::Truncated, Cause::Corrupted],
+        ));
+    }
+    let key = &message_key(key, header.nonce.as_ref());
+    let (dna, ambig) = playfair::split_cipher_with_key(cipher, config.ambig_pos, key);
+    de.dna = dna.iter().collect();
+    de.ambig_vec = ambig;
+    if de.dna.is_empty() && de.ambig_vec.is_empty() {
+        return de;
+    }
+    if de.dna.len() != de.ambig_vec.len() * 3 {
+        let swapped = CipherMap::new(cipher, !config.ambig_pos);
+        return de.fail(match swapped.dna_len() == swapped.ambig_len() * 3 {
+            true => Diagnostic::new(
+                DecryptStage::Split,
+                "the DNA and ambiguity sections are the other way round",
+                (0..2)
+                    .flat_map(|i| map.section(i + (sections > 2) as usize))
+                    .collect(),
+                &[Cause::WrongLayout],
+            ),
+            false => {
+                // Whatever the shorter side cannot account for.
+                let acids = (map.dna_len() / 3).min(map.ambig_len());
+                let mut offsets = map.dna(acids * 3..map.dna_len());
+                offsets.extend(map.ambig(acids..map.ambig_len()));
+                Diagnostic::new(
+                    DecryptStage::Split,
+                    format!(
+                        "{} nucleotides do not match {} ambiguity digits",
+                        map.dna_len(),
+                        map.ambig_len()
+                    ),
+                    offsets,
+                    &[Cause::Truncated, Cause::Corrupted],
+                )
+            }
+        });
+    }
+    let table = config.stage.codon_table();
+    let (acids, _) = playfair::dna_to_acids_with(&dna, table);
+    let unknown: Vec<usize> = dna
+        .chunks(3)
+        .enumerate()
+        .filter(|(_, codon)| codon.contains(&'N'))
+        .map(|(i, _)| i)
+        .collect();
+    if !unknown.is_empty() {
+        return de.fail(Diagnostic::new(
+            DecryptStage::CodonTranslation,
+            format!(
+                "{} codons contain N, which only the ambiguity stream uses",
+                unknown.len()
+            ),
+            unknown
+                .iter()
+                .take(MAX_MARKED)
+                .flat_map(|&i| map.dna(i * 3..i * 3 + 3))
+                .collect(),
+            &[Cause::WrongLayout, Cause::Corrupted],
+        ));
+    }
+    de.acids = acids.iter().collect();
+    let pairs = matches!(config.stage, Stage::Playfair | Stage::PlayfairBifid(_));
+    if pairs && acids.len() % 2 == 1 {
+        return de.fail(Diagnostic::new(
+            DecryptStage::Playfair,
+            format!("{} acids cannot be split into Playfair pairs", acids.len()),
+            map.acid(acids.len() - 1, true),
+            &[Cause::Truncated, Cause::WrongSettings],
+        ));
+    }
+
+    let unsanitized_acids = rounds::decrypt(&config.stage, key, &de.acids, config.rounds);
+    let decrypted_acids: Vec<char> = unsanitized_acids.chars().collect();
+    // Positions of the cipher acids survive only a single plain Playfair round.
+    let positional = config.stage == Stage::Playfair && config.rounds <= 1;
+    let fillers: Vec<usize> = (0..de.ambig_vec.len())
+        .filter(|&i| de.ambig_vec[i] == 4)
+        .collect();
+    let misplaced: Vec<usize> = fillers
+        .iter()
+        .copied()
+        .filter(|&i| !pairs || decrypted_acids[i] != 'X')
+        .collect();
+    if !misplaced.is_empty() {
+        let message = match pairs {
+            true => format!(
+                "{} of {} filler marks do not decrypt to X",
+                misplaced.len(),
+                fillers.len()
+            ),
+            false => format!(
+                "{} filler marks, but {} inserts no fillers",
+                misplaced.len(),
+                config.stage.string()
+            ),
+        };
+        let causes = match (pairs, header.mask_ambig) {
+            (true, _) => [Cause::WrongKey, Cause::Corrupted],
+            (false, true) => [Cause::WrongKey, Cause::WrongSettings],
+            (false, false) => [Cause::WrongSettings, Cause::Corrupted],
+        };
+        de.diagnostics.push(Diagnostic::new(
+            DecryptStage::FillerStripping,
+            message,
+            mark_acids(&map, &misplaced, positional),
+            &causes,
+        ));
+    }
+    de.acids_after_playfair = playfair::sanitize_acids(&unsanitized_acids, &de.ambig_vec);
+    let mut sanitized_ambig_vector = de.ambig_vec.clone();
+    playfair::sanitize_ambig(&mut sanitized_ambig_vector);
+    de.dna_after_playfair = playfair::acids_to_dna_with(
+        &de.acids_after_playfair.chars().collect_vec(),
+        &sanitized_ambig_vector,
+        table,
+    )
+    .iter()
+    .collect();
+
+    // Cipher acid index of every plaintext acid.
+    let kept: Vec<usize> = (0..de.ambig_vec.len())
+        .filter(|&i| de.ambig_vec[i] != 4)
+        .collect();
+    let invalid: Vec<usize> = de
+        .dna_after_playfair
+        .chars()
+        .chunks(3)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(j, mut codon)| codon.any(|c| c == '-').then_some(kept[j]))
+        .collect();
+    if !invalid.is_empty() {
+        de.diagnostics.push(Diagnostic::new(
+            DecryptStage::Playfair,
+            format!(
+                "{} decrypted acids have no codon for their ambiguity digit",
+                invalid.len()
+            ),
+            mark_acids(&map, &invalid, positional),
+            &[Cause::WrongKey, Cause::WrongSettings],
+        ));
+    }
+    if !kept.len().is_multiple_of(4) {
+        de.diagnostics.push(Diagnostic::new(
+            DecryptStage::Binary,
+            format!(
+                "{} plaintext acids do not make whole bytes, encryption always gives a multiple of 4",
+                kept.len()
+            ),
+            map.acid(de.ambig_vec.len() - 1, true),
+            &[Cause::Truncated, Cause::WrongKey],
+        ));
+    }
+
+    de.binary = playfair::dna_to_binary(&de.dna_after_playfair);
+    if let Some(tag) = tag {
+        if !mac::verify(key, &config.authenticated(&header), &de.binary, &tag) {
+            de.status = Status::BadTag;
+            de.plain_text = "authentication failed - wrong key or modified cipher!".to_string();
+            de.diagnostics.push(Diagnostic::new(
+                DecryptStage::Authentication,
+                "the tag does not match the decrypted text and settings",
+                map.section(sections - 1),
+                &[Cause::WrongKey, Cause::WrongSettings, Cause::Corrupted],
+            ));
+            return de;
+        }
+    }
+    (de.plain_text, de.status) = match decode(&de.binary, config.text_format) {
+        Ok(v) if header.mac => (v, Status::Verified),
+        Ok(v) => (v, Status::Decrypted),
+        Err((valid, e)) => {
+            // Four nucleotides per byte, three per acid.
+            let acid = kept[(valid * 4 / 3).min(kept.len() - 1)];
+            de.diagnostics.push(Diagnostic::new(
+                DecryptStage::TextDecode,
+                format!(
+                    "invalid {} after {} of {} bytes",
+                    config.text_format.string(),
+                    valid,
+                    de.binary.len()
+                ),
+                map.acid(acid, positional),
+                match valid * 2 >= de.binary.len() {
+                    true => &[Cause::Corrupted, Cause::WrongSettings],
+                    false => &[Cause::WrongKey, Cause::WrongSettings],
+                },
+            ));
+            (
+                format!("wrong binary format - check your key! \n err: {}", e),
+                Status::InvalidText,
+            )
+        }
+    };
+    de
+}
@@ -0,0 +1,378 @@
+use super::{Config, Encodings, Stage};
+use crate::playfair::{kdf::KdfParams, nonce};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+// Default pipeline and output options from TOML files. The per-user file is
+// read first, then the nearest `playfair-dna.toml` of the working directory
+// or one of its parents overrides it key by key. Unset keys keep the
+// built-in defaults. The codon table follows the stage.
+
+pub const FILE_NAME: &str = "playfair-dna.toml";
+const FASTA_WIDTH: usize = 60;
+/// Read only this file instead of searching.
+pub const ENV: &str = "PLAYFAIR_DNA_CONFIG";
+
+// The option enums are shared with the command line, and `Layout`,
+// `Encoding` and `StageName` are also written to batch manifests.
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    /// AMBIG-DNA
+    Before,
+    /// DNA-AMBIG
+    After,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Encoding {
+    Utf8,
+    Utf16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum StageName {
+    Playfair,
+    Bifid,
+    PlayfairBifid,
+    Trifid,
+    Adfgx,
+}
+
+/// U as in RNA, the cipher's own alphabet, or T as in DNA sequence files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, ValueEnum)]
+pub enum Alphabet {
+    /// Uracil, as the GUI writes it
+    #[default]
+    U,
+    /// Thymine, for DNA sequence tools
+    T,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// The bare cipher
+    #[default]
+    Cipher,
+    /// A FASTA record named after the input
+    Fasta,
+}
+
+impl Format {
+    /// File name extension of a cipher written in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Cipher => "dna",
+            Format::Fasta => "fasta",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    pub layout: Option<Layout>,
+    pub encoding: Option<Encoding>,
+    pub stage: Option<StageName>,
+    pub period: Option<usize>,
+    pub transposition: Option<String>,
+    pub rounds: Option<usize>,
+    pub passphrase: Option<bool>,
+    pub mask_ambig: Option<bool>,
+    pub nonce: Option<bool>,
+    pub mac: Option<bool>,
+    pub alphabet: Option<Alphabet>,
+    /// Nucleotides per line, 0 for a single line.
+    pub wrap: Option<usize>,
+    pub format: Option<Format>,
+}
+
+#[derive(Debug)]
+pub enum DefaultsError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    /// Options that are each valid but do not make a working pipeline.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for DefaultsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefaultsError::Io(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            DefaultsError::Parse(path, e) => write!(f, "invalid {}: {}", path.display(), e),
+            DefaultsError::Invalid(reason) => write!(f, "invalid options: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for DefaultsError {}
+
+/// How a finished cipher is written out.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Output {
+    pub alphabet: Alphabet,
+    pub wrap: usize,
+    pub format: Format,
+}
+
+impl Output {
+    /// The cipher in the configured alphabet, line width and format. `name`
+    /// becomes the FASTA record name.
+    pub fn render(&self, cipher: &str, name: &str) -> String {
+        let cipher = match self.alphabet {
+            Alphabet::U => cipher.to_string(),
+            Alphabet::T => cipher.replace('U', "T"),
+        };
+        let wrap = match (self.wrap, self.format) {
+            (0, Format::Fasta) => FASTA_WIDTH,
+            (0, Format::Cipher) => return cipher + "\n",
+            (wrap, _) => wrap,
+        };
+        let mut out = match self.format {
+            Format::Cipher => String::new(),
+            Format::Fasta => format!(">{}\n", name),
+        };
+        for line in cipher.as_bytes().chunks(wrap) {
+            out += &String::from_utf8_lossy(line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// The cipher of any rendered output: FASTA name lines are blanked, so
+/// diagnostic offsets still point into `text`. The decrypt side already
+/// ignores whitespace and reads T as U.
+pub fn strip_fasta(text: &str) -> String {
+    text.split_inclusive('\n')
+        .map(|line| match line.starts_with('>') {
+            true => line
+                .chars()
+                .map(|c| if c == '\n' { c } else { ' ' })
+                .collect(),
+            false => line.to_string(),
+        })
+        .collect()
+}
+
+impl Defaults {
+    /// `self` with every key set in `other` replaced.
+    pub fn merge(self, other: Defaults) -> Defaults {
+        Defaults {
+            layout: other.layout.or(self.layout),
+            encoding: other.encoding.or(self.encoding),
+            stage: other.stage.or(self.stage),
+            period: other.period.or(self.period),
+            transposition: other.transposition.or(self.transposition),
+            rounds: other.rounds.or(self.rounds),
+            passphrase: other.passphrase.or(self.passphrase),
+            mask_ambig: other.mask_ambig.or(self.mask_ambig),
+            nonce: other.nonce.or(self.nonce),
+            mac: other.mac.or(self.mac),
+            alphabet: other.alphabet.or(self.alphabet),
+            wrap: other.wrap.or(self.wrap),
+            format: other.format.or(self.format),
+        }
+    }
+
+    /// Only the keys both sides have to agree on; the decrypt side reads the
+    /// rest from the cipher header.
+    pub fn pipeline(&self) -> Defaults {
+        Defaults {
+            layout: self.layout,
+            encoding: self.encoding,
+            stage: self.stage,
+            period: self.period,
+            transposition: self.transposition.clone(),
+            rounds: self.rounds,
+            ..Defaults::default()
+        }
+    }
+
+    /// `config` with the keys that are set. A passphrase or nonce gets fresh
+    /// random parameters, and masking always brings a nonce. Fails on a stage
+    /// that would not encrypt, such as ADFGX without a transposition keyword.
+    pub fn apply(&self, config: Config) -> Result<Config, DefaultsError> {
+        let period = self.period.or(config.stage.period()).unwrap_or(5);
+        let stage = match self.stage {
+            None => config.stage.with_period(period),
+            Some(StageName::Playfair) => Stage::Playfair,
+            Some(StageName::Bifid) => Stage::Bifid(period),
+            Some(StageName::PlayfairBifid) => Stage::PlayfairBifid(period),
+            Some(StageName::Trifid) => Stage::Trifid(period),
+            Some(StageName::Adfgx) => Stage::Adfgx(String::new()),
+        };
+        let stage = match (stage, &self.transposition) {
+            (Stage::Adfgx(_), Some(keyword)) => Stage::Adfgx(keyword.clone()),
+            (stage, _) => stage,
+        };
+        if let Some(reason) = stage.error() {
+            return Err(DefaultsError::Invalid(reason));
+        }
+        let mask_ambig = self.mask_ambig.unwrap_or(config.mask_ambig);
+        Ok(Config {
+            ambig_pos: self
+                .layout
+                .map_or(config.ambig_pos, |l| l == Layout::Before),
+            text_format: match self.encoding {
+                Some(Encoding::Utf8) => Encodings::UTF8,
+                Some(Encoding::Utf16) => Encodings::UTF16,
+                None => config.text_format,
+            },
+            stage,
+            rounds: self.rounds.unwrap_or(config.rounds).max(1),
+            kdf: match self.passphrase {
+                Some(true) => Some(config.kdf.unwrap_or_else(KdfParams::random)),
+                Some(false) => None,
+                None => config.kdf,
+            },
+            mask_ambig,
+            nonce: match (self.nonce, mask_ambig) {
+                (Some(true), _) | (_, true) => Some(config.nonce.unwrap_or_else(nonce::random)),
+                (Some(false), false) => None,
+                (None, false) => config.nonce,
+            },
+            mac: self.mac.unwrap_or(config.mac),
+        })
+    }
+
+    pub fn config(&self) -> Result<Config, DefaultsError> {
+        self.apply(Config::default())
+    }
+
+    pub fn output(&self) -> Output {
+        Output {
+            alphabet: self.alphabet.unwrap_or_default(),
+            wrap: self.wrap.unwrap_or(0),
+            format: self.format.unwrap_or_default(),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/playfair-dna/config.toml`, `~/.config/...` or
+/// `%APPDATA%\playfair-dna\config.toml`.
+pub fn user_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(dir.join("playfair-dna").join("config.toml"))
+}
+
+/// The nearest `playfair-dna.toml` in `dir` or its parents.
+pub fn project_path(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+pub fn load_file(path: &Path) -> Result<Defaults, DefaultsError> {
+    let text = fs::read_to_string(path).map_err(|e| DefaultsError::Io(path.to_path_buf(), e))?;
+    toml::from_str(&text).map_err(|e| DefaultsError::Parse(path.to_path_buf(), e))
+}
+
+/// The file named by `PLAYFAIR_DNA_CONFIG`, or the user file overridden by
+/// the project file. Missing files are not an error.
+pub fn load() -> Result<Defaults, DefaultsError> {
+    if let Some(path) = env::var_os(ENV) {
+        return load_file(Path::new(&path));
+    }
+    let mut defaults = Defaults::default();
+    let project = env::current_dir().ok().and_then(|dir| project_path(&dir));
+    for path in [user_path(), project].into_iter().flatten() {
+        if path.is_file() {
+            defaults = defaults.merge(load_file(&path)?);
+        }
+    }
+    Ok(defaults)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_layers_override_set_keys_only() {
+        let user = Defaults {
+            layout: Some(Layout::After),
+            stage: Some(StageName::Bifid),
+            period: Some(7),
+            mac: Some(true),
+            wrap: Some(60),
+            ..Defaults::default()
+        };
+        let project = Defaults {
+            stage: Some(StageName::Trifid),
+            mac: Some(false),
+            ..Defaults::default()
+        };
+        // As the command line layers its options over the files
+        let command_line = Defaults {
+            period: Some(3),
+            ..Defaults::default()
+        };
+        let merged = user.merge(project).merge(command_line);
+        assert_eq!(merged.layout, Some(Layout::After));
+        assert_eq!(merged.stage, Some(StageName::Trifid));
+        assert_eq!(merged.period, Some(3));
+        assert_eq!(merged.mac, Some(false));
+        assert_eq!(merged.wrap, Some(60));
+        assert_eq!(merged.encoding, None);
+
+        let config = merged.config().unwrap();
+        assert!(!config.ambig_pos);
+        assert_eq!(config.stage, Stage::Trifid(3));
+        assert!(!config.mac);
+        assert_eq!(merged.output().wrap, 60);
+        assert_eq!(merged.output().format, Format::Cipher);
+    }
+
+    #[test]
+    fn unset_keys_keep_the_given_config() {
+        let config = Config {
+            ambig_pos: false,
+            text_format: Encodings::UTF16,
+            stage: Stage::Bifid(9),
+            rounds: 3,
+            kdf: Some(KdfParams::default()),
+            mac: true,
+            ..Config::default()
+        };
+        assert_eq!(Defaults::default().apply(config.clone()).unwrap(), config);
+
+        // A stage without a period keeps the one configured before
+        let stage = Defaults {
+            stage: Some(StageName::Trifid),
+            rounds: Some(0),
+            passphrase: Some(false),
+            ..Defaults::default()
+        };
+        let applied = stage.apply(config.clone()).unwrap();
+        assert_eq!(applied.stage, Stage::Trifid(9));
+        assert_eq!(applied.rounds, 1);
+        assert_eq!(applied.kdf, None);
+        assert_eq!(applied.text_format, Encodings::UTF16);
+        assert!(applied.mac);
+
+        assert_eq!(Defaults::default().config().unwrap(), Config::default());
+    }
+
+    #[test]
+    fn masking_brings_a_nonce() {
+        let defaults = Defaults {
+            mask_ambig: Some(true),
+            nonce: Some(false),
+            ..Defaults::default()
+        };
+        let config = defaults.config().unwrap();
+        assert!(config.mask_ambig);
+        assert!(config.nonce.is_some());
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+use std::ops::Range;
+
+// Decrypt diagnostics. Each finding names the step that went wrong, the
+// characters of the cipher (as typed, counted in chars) it points at and the
+// usual reasons for it. Offsets into the DNA section are only exact for a
+// single Playfair round; the ambiguity digits always line up with the
+// plaintext acids.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DecryptStage {
+    Split,
+    CodonTranslation,
+    Playfair,
+    FillerStripping,
+    Binary,
+    TextDecode,
+    Authentication,
+}
+
+impl DecryptStage {
+    pub fn string(&self) -> &str {
+        match self {
+            DecryptStage::Split => "split",
+            DecryptStage::CodonTranslation => "codon translation",
+            DecryptStage::Playfair => "Playfair",
+            DecryptStage::FillerStripping => "filler stripping",
+            DecryptStage::Binary => "binary",
+            DecryptStage::TextDecode => "text decode",
+            DecryptStage::Authentication => "authentication",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cause {
+    WrongLayout,
+    Truncated,
+    WrongKey,
+    WrongSettings,
+    Corrupted,
+}
+
+impl Cause {
+    pub fn string(&self) -> &str {
+        match self {
+            Cause::WrongLayout => "wrong ambiguity layout (before/after)",
+            Cause::Truncated => "truncated cipher",
+            Cause::WrongKey => "wrong key",
+            Cause::WrongSettings => "stage, rounds or encoding differ from the encrypt side",
+            Cause::Corrupted => "modified or corrupted cipher",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub stage: DecryptStage,
+    pub message: String,
+    /// Char ranges of the cipher text that the problem points at.
+    pub offsets: Vec<Range<usize>>,
+    /// Most likely first.
+    pub causes: Vec<Cause>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        stage: DecryptStage,
+        message: impl Into<String>,
+        offsets: Vec<Range<usize>>,
+        causes: &[Cause],
+    ) -> Self {
+        Self {
+            stage,
+            message: message.into(),
+            offsets,
+            causes: causes.to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.stage.string(), self.message)?;
+        if !self.causes.is_empty() {
+            let causes: Vec<&str> = self.causes.iter().map(|c| c.string()).collect();
+            write!(f, " - likely {}", causes.join(", or "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Contiguous runs of sorted indices as ranges.
+fn ranges(indices: impl Iterator<Item = usize>) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for i in indices {
+        match ranges.last_mut() {
+            Some(last) if last.end == i => last.end = i + 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    ranges
+}
+
+/// Where every nucleotide of a cipher sits in the text as typed.
+pub struct CipherMap {
+    sections: Vec<Vec<usize>>,
+    ignored: Vec<usize>,
+    before: bool,
+}
+
+impl CipherMap {
+    pub fn new(text: &str, before: bool) -> Self {
+        let mut sections: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut ignored: Vec<usize> = Vec::new();
+        for (i, c) in text.chars().enumerate() {
+            match c {
+                '-' => sections.push(Vec::new()),
+                'A' | 'C' | 'G' | 'U' | 'T' | 'N' => sections.last_mut().unwrap().push(i),
+                c if c.is_whitespace() => {}
+                _ => ignored.push(i),
+            }
+        }
+        Self {
+            sections,
+            ignored,
+            before,
+        }
+    }
+
+    pub fn section_count(&self) -> usize {
+        self.sections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.iter().all(|s| s.is_empty())
+    }
+
+    /// Characters `split_cipher` drops silently.
+    pub fn ignored(&self) -> Vec<Range<usize>> {
+        ranges(self.ignored.iter().copied())
+    }
+
+    pub fn section(&self, i: usize) -> Vec<Range<usize>> {
+        match self.sections.get(i) {
+            Some(section) => ranges(section.iter().copied()),
+            None => Vec::new(),
+        }
+    }
+
+    fn body(&self, ambig: bool) -> &[usize] {
+        let first = match self.sections.len() {
+            3 | 4 => 1,
+            _ => 0,
+        };
+        let index = first + (ambig != self.before) as usize;
+        self.sections.get(index).map_or(&[], |s| s.as_slice())
+    }
+
+    pub fn dna_len(&self) -> usize {
+        self.body(false).len()
+    }
+
+    pub fn ambig_len(&self) -> usize {
+        self.body(true).len()
+    }
+
+    pub fn dna(&self, nucleotides: Range<usize>) -> Vec<Range<usize>> {
+        let section = self.body(false);
+        ranges(
+            section[nucleotides.start.min(section.len())..nucleotides.end.min(section.len())]
+                .iter()
+                .copied(),
+        )
+    }
+
+    pub fn ambig(&self, digits: Range<usize>) -> Vec<Range<usize>> {
+        let section = self.body(true);
+        ranges(
+            section[digits.start.min(section.len())..digits.end.min(section.len())]
+                .iter()
+                .copied(),
+        )
+    }
+
+    /// The ambiguity digit of an acid and, where positions survive the stage,
+    /// its codon in the DNA section.
+    pub fn acid(&self, acid: usize, with_codon: bool) -> Vec<Range<usize>> {
+        let mut ranges = self.ambig(acid..acid + 1);
+        if with_codon {
+            ranges.extend(self.dna(acid * 3..acid * 3 + 3));
+        }
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{self, Config, Status};
+    use crate::playfair;
+
+    const TEXT: &str = "Diagnostics point at the damaged part of a cipher.";
+    const NUCLEOTIDE: usize = 7;
+
+    /// Encrypts `TEXT` and swaps one DNA nucleotide, returning the cipher and
+    /// the char index of the swapped nucleotide.
+    fn corrupted(config: &Config, with: impl Fn(char) -> char) -> (String, usize) {
+        let key = playfair::generate_key_matrix("diagnostics");
+        let cipher = pipeline::encrypt(TEXT, &key, config).cipher(config);
+        let map = CipherMap::new(&cipher, config.ambig_pos);
+        let index = map.dna(NUCLEOTIDE..NUCLEOTIDE + 1)[0].start;
+        let cipher = cipher
+            .chars()
+            .enumerate()
+            .map(|(i, c)| if i == index { with(c) } else { c })
+            .collect();
+        (cipher, index)
+    }
+
+    fn decrypt(cipher: &str, config: &Config) -> pipeline::Decrypted {
+        let key = playfair::generate_key_matrix("diagnostics");
+        pipeline::decrypt(cipher, &key, config)
+    }
+
+    #[test]
+    fn an_unknown_base_marks_its_codon() {
+        let config = Config::default();
+        let (cipher, index) = corrupted(&config, |_| 'N');
+        let de = decrypt(&cipher, &config);
+        let diagnostic = de.diagnostics.last().unwrap();
+        assert_eq!(diagnostic.stage, DecryptStage::CodonTranslation);
+        // The codon of the swapped nucleotide, as typed
+        let map = CipherMap::new(&cipher, config.ambig_pos);
+        let codon = NUCLEOTIDE / 3 * 3;
+        assert_eq!(diagnostic.offsets, map.dna(codon..codon + 3));
+        assert!(diagnostic.offsets.iter().any(|r| r.contains(&index)));
+        assert_eq!(diagnostic.causes, [Cause::WrongLayout, Cause::Corrupted]);
+    }
+
+    #[test]
+    fn a_swapped_base_fails_the_tag() {
+        let config = Config {
+            mac: true,
+            ..Config::default()
+        };
+        let swap = |c| if c == 'A' { 'C' } else { 'A' };
+        let (cipher, _) = corrupted(&config, swap);
+        let de = decrypt(&cipher, &config);
+        assert_eq!(de.status, Status::BadTag);
+        let diagnostic = de.diagnostics.last().unwrap();
+        assert_eq!(diagnostic.stage, DecryptStage::Authentication);
+        let map = CipherMap::new(&cipher, config.ambig_pos);
+        assert_eq!(map.section_count(), 4);
+        assert_eq!(diagnostic.offsets, map.section(3));
+        assert!(diagnostic.causes.contains(&Cause::Corrupted));
+    }
+}
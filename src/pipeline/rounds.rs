@@ -0,0 +1,127 @@
+use super::Stage;
+use crate::playfair::{keygen, transposition};
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+
+// Cascaded rounds. Only the first round may insert Playfair fillers; later
+// rounds keep the length, so the ambiguity stream (and its filler marks)
+// stays aligned with the first round's output throughout. Between rounds the
+// acids go through a columnar transposition keyed by the next round's matrix.
+
+/// Matrix for a round: the master key itself for round 0, a hash-derived
+/// permutation for every later round.
+pub fn round_key(master: &[char], round: usize) -> Vec<char> {
+    if round == 0 {
+        return master.to_vec();
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(b"playfair-dna round key");
+    hasher.update(master.iter().collect::<String>().as_bytes());
+    hasher.update((round as u32).to_be_bytes());
+    keygen::key_from_bytes(&hasher.finalize())
+}
+
+fn keyword(key: &[char]) -> String {
+    key.iter().collect()
+}
+
+pub fn encrypt(
+    stage: &Stage,
+    master: &[char],
+    acids: &str,
+    ambig_vec: &mut Vec<u8>,
+    rounds: usize,
+) -> String {
+    let mut text = stage.encrypt(master, acids, ambig_vec);
+    for round in 1..rounds {
+        let key = round_key(master, round);
+        let transposed: String =
+            transposition::transpose(&text.chars().collect_vec(), &keyword(&key))
+                .iter()
+                .collect();
+        text = stage.encrypt_round(&key, &transposed);
+    }
+    text
+}
+
+/// Undoes `encrypt` round by round, last round first.
+pub fn decrypt(stage: &Stage, master: &[char], acids: &str, rounds: usize) -> String {
+    let mut text = acids.to_string();
+    for round in (1..rounds).rev() {
+        let key = round_key(master, round);
+        let decrypted = stage.decrypt(&key, &text);
+        text = transposition::untranspose(&decrypted.chars().collect_vec(), &keyword(&key))
+            .iter()
+            .collect();
+    }
+    stage.decrypt(master, &text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{self, Config};
+    use crate::playfair;
+
+    const TEXTS: [&str; 5] = [
+        "",
+        "a",
+        "balloon keeper, committee",
+        "AAAA XX xx",
+        "héllo wörld 🧬🧬",
+    ];
+
+    fn stages() -> Vec<Stage> {
+        vec![
+            Stage::Playfair,
+            Stage::Bifid(5),
+            Stage::PlayfairBifid(3),
+            Stage::Trifid(0),
+            Stage::Adfgx("CASCADE".to_string()),
+        ]
+    }
+
+    #[test]
+    fn round_keys_differ_from_master() {
+        let master = playfair::generate_key_matrix("cascade");
+        assert_eq!(round_key(&master, 0), master);
+        for round in 1..4 {
+            assert_ne!(round_key(&master, round), master);
+            assert_ne!(round_key(&master, round), round_key(&master, round + 1));
+        }
+    }
+
+    #[test]
+    fn reverse_order_decryption_recovers_plaintext() {
+        let key = playfair::generate_key_matrix("cascade");
+        for stage in stages() {
+            for rounds in 1..=4 {
+                let config = Config {
+                    stage: stage.clone(),
+                    rounds,
+                    ..Default::default()
+                };
+                for text in TEXTS {
+                    let en = pipeline::encrypt(text, &key, &config);
+                    let de = pipeline::decrypt(&en.cipher(&config), &key, &config);
+                    assert_eq!(de.acids_after_playfair, en.acids, "{:?} x{}", stage, rounds);
+                    assert_eq!(de.dna_after_playfair, en.dna, "{:?} x{}", stage, rounds);
+                    assert_eq!(de.binary, playfair::utf8_to_binary(text));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rounds_keep_ambiguity_aligned() {
+        let key = playfair::generate_key_matrix("cascade");
+        let acids = "AABBLLOOQ";
+        let mut single = vec![0u8; acids.len()];
+        let mut cascaded = vec![0u8; acids.len()];
+        let once = encrypt(&Stage::Playfair, &key, acids, &mut single, 1);
+        let thrice = encrypt(&Stage::Playfair, &key, acids, &mut cascaded, 3);
+        assert_eq!(single, cascaded);
+        assert_eq!(once.len(), thrice.len());
+        assert_ne!(once, thrice);
+    }
+}
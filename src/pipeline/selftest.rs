@@ -0,0 +1,163 @@
+use super::{decrypt, encrypt, Config, Encodings, Stage, Status};
+use crate::playfair::{self, nonce};
+
+// A quick field check of an installed build: a fixed corpus goes through
+// encryption and decryption under a spread of settings and has to come back
+// byte for byte.
+
+const KEY: &str = "selftest";
+
+/// Texts chosen for the edge cases of the pipeline.
+pub const CORPUS: &[(&str, &str)] = &[
+    ("ascii", "The quick brown fox jumps over the lazy dog."),
+    ("multi-byte utf-8", "Grüße aus Köln, déjà vu – naïve Ærø"),
+    ("emoji", "🧬 DNA 🔐 playfair 👍🏽"),
+    ("empty", ""),
+    ("one byte", "a"),
+    ("two bytes", "ab"),
+    ("doubled letters", "aaaaaaaaaaaaUUUU"),
+    ("odd acid count", "UUU"),
+    ("trailing spaces", "padding   "),
+    ("trailing control", "tab\tnewline\n\u{1}"),
+];
+
+/// Result of one corpus text under one configuration.
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    pub case: &'static str,
+    pub settings: String,
+    pub passed: bool,
+    /// Why it failed, empty when it passed.
+    pub detail: String,
+}
+
+fn label(config: &Config) -> String {
+    let mut label = format!(
+        "{} x{}, {}, {}",
+        config.stage.string(),
+        config.rounds,
+        match config.ambig_pos {
+            true => "before",
+            false => "after",
+        },
+        config.text_format.string()
+    );
+    for (set, option) in [
+        (config.mask_ambig, "masked"),
+        (config.nonce.is_some(), "nonce"),
+        (config.mac, "tag"),
+    ] {
+        if set {
+            label += ", ";
+            label += option;
+        }
+    }
+    label
+}
+
+/// Both layouts and encodings with the Playfair stage, then every other
+/// stage, several rounds and all header options once.
+pub fn configs() -> Vec<Config> {
+    let mut configs: Vec<Config> = Vec::new();
+    for ambig_pos in [true, false] {
+        for text_format in [Encodings::UTF8, Encodings::UTF16] {
+            configs.push(Config {
+                ambig_pos,
+                text_format,
+                ..Config::default()
+            });
+        }
+    }
+    for stage in [
+        Stage::Bifid(5),
+        Stage::PlayfairBifid(7),
+        Stage::Trifid(5),
+        Stage::Adfgx("helix".to_string()),
+    ] {
+        configs.push(Config {
+            stage,
+            ..Config::default()
+        });
+    }
+    configs.push(Config {
+        rounds: 3,
+        ..Config::default()
+    });
+    configs.push(Config {
+        ambig_pos: false,
+        text_format: Encodings::UTF16,
+        mask_ambig: true,
+        nonce: Some(nonce::random()),
+        mac: true,
+        ..Config::default()
+    });
+    configs
+}
+
+pub fn check(case: &'static str, text: &str, config: &Config) -> Outcome {
+    let key = playfair::generate_key_matrix(KEY);
+    let cipher = encrypt(text, &key, config).cipher(config);
+    let de = decrypt(&cipher, &key, config);
+    let expected = match (text.is_empty(), config.mac) {
+        (true, _) => Status::Empty,
+        (false, true) => Status::Verified,
+        (false, false) => Status::Decrypted,
+    };
+    let detail = if de.status != expected {
+        format!(
+            "status \"{}\", expected \"{}\"",
+            de.status.string(),
+            expected.string()
+        )
+    } else if de.plain_text != text {
+        format!("decrypted to {:?}", de.plain_text)
+    } else {
+        String::new()
+    };
+    Outcome {
+        case,
+        settings: label(config),
+        passed: detail.is_empty(),
+        detail,
+    }
+}
+
+/// Every corpus text under every configuration.
+pub fn run() -> Vec<Outcome> {
+    let configs = configs();
+    CORPUS
+        .iter()
+        .flat_map(|&(case, text)| configs.iter().map(move |config| check(case, text, config)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corpus_round_trips() {
+        for outcome in run() {
+            assert!(
+                outcome.passed,
+                "{} ({}): {}",
+                outcome.case, outcome.settings, outcome.detail
+            );
+        }
+    }
+
+    #[test]
+    fn labels_name_each_header_option() {
+        let tagged = Config {
+            mac: true,
+            ..Config::default()
+        };
+        assert_eq!(label(&tagged), "Playfair x1, before, UTF-8, tag");
+        let masked = Config {
+            mask_ambig: true,
+            nonce: Some(nonce::random()),
+            ..Config::default()
+        };
+        assert_eq!(label(&masked), "Playfair x1, before, UTF-8, masked, nonce");
+    }
+}
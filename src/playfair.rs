@@ -1,10 +1,22 @@
+pub mod adfgx;
 mod constants;
+pub mod fractionation;
+pub mod header;
+pub mod kdf;
+pub mod keygen;
+pub mod mac;
+pub mod mask;
+pub mod nonce;
+pub mod strength;
+pub mod transposition;
 use itertools::Itertools;
 
-pub fn generate_key_matrix(string: &String) -> Vec<char> {
+pub use constants::ALPHABET;
+
+pub fn generate_key_matrix(string: &str) -> Vec<char> {
     let mut key = string.to_uppercase();
     key = key.replace("J", "I");
-    let mut alpha: Vec<char> = "ABCDEFGHIKLMNOPQRSTUVWXYZ".chars().collect();
+    let mut alpha: Vec<char> = constants::ALPHABET.chars().collect();
     let mut key: Vec<char> = key.chars().collect();
     key.retain(|&c| !c.is_whitespace());
     key.retain(|&c| c.is_alphabetic());
@@ -14,23 +26,38 @@ pub fn generate_key_matrix(string: &String) -> Vec<char> {
 }
 
 fn is_dna_cipher(c: char) -> bool {
-    c == 'A' || c == 'U' || c == 'C' || c == 'G' || c == 'N' || c == '-'
+    c == 'A' || c == 'U' || c == 'T' || c == 'C' || c == 'G' || c == 'N' || c == '-'
 }
 
-pub fn split_cipher(text: &String, before: bool) -> (Vec<char>, Vec<u8>) {
+/// The `-` separated nucleotide sections of a cipher, other characters
+/// dropped and T read as U.
+pub fn cipher_sections(text: &str) -> Vec<String> {
     let mut text: Vec<char> = text.chars().collect();
     text.retain(|&c| is_dna_cipher(c));
+    let text: Vec<char> = text
+        .iter()
+        .map(|&c| if c == 'T' { 'U' } else { c })
+        .collect();
     let text: String = text.iter().collect();
-    let text = text.split("-").collect::<Vec<&str>>();
+    text.split("-").map(|s| s.to_string()).collect()
+}
+
+pub fn split_cipher(text: &str, before: bool) -> (Vec<char>, Vec<u8>) {
+    let mut text = cipher_sections(text);
+    if text.len() == 4 {
+        text.pop(); // tag
+    }
+    if text.len() == 3 {
+        text.remove(0); // header
+    }
     if text.len() != 2 {
         return (Vec::new(), Vec::new());
     }
-    let dna: Vec<char>;
     let (dna_index, ambig_index) = match before {
         true => (1usize, 0usize),
-        false => (0usize, 0usize),
+        false => (0usize, 1usize),
     };
-    dna = text[dna_index].chars().collect();
+    let dna: Vec<char> = text[dna_index].chars().collect();
     let mut ambig: Vec<u8> = Vec::with_capacity(text[ambig_index].len());
     for c in text[ambig_index].chars() {
         ambig.push(constants::DNA_REVERSE[&c]);
@@ -38,29 +65,125 @@ pub fn split_cipher(text: &String, before: bool) -> (Vec<char>, Vec<u8>) {
     (dna, ambig)
 }
 
-pub fn utf8_to_binary(text: &String) -> Vec<u8> {
+/// `split_cipher` for the decrypt side: a masked ambiguity stream, as flagged
+/// in the header, is unmasked with the key matrix.
+pub fn split_cipher_with_key(text: &str, before: bool, key: &[char]) -> (Vec<char>, Vec<u8>) {
+    let (dna, ambig) = split_cipher(text, before);
+    match cipher_header(text) {
+        Some(header) if header.mask_ambig => {
+            (dna, mask::unmask(key, header.nonce.as_ref(), &ambig))
+        }
+        _ => (dna, ambig),
+    }
+}
+
+pub fn cipher_header(text: &str) -> Option<header::Header> {
+    let text = cipher_sections(text);
+    if text.len() != 3 && text.len() != 4 {
+        return None;
+    }
+    header::Header::decode(&text[0])
+}
+
+/// The authentication tag of a cipher whose header announces one.
+pub fn cipher_tag(text: &str) -> Option<mac::Tag> {
+    let text = cipher_sections(text);
+    if text.len() != 4 {
+        return None;
+    }
+    mac::decode(&text[3])
+}
+
+pub fn with_header(header: &header::Header, cipher: &str) -> String {
+    if header.is_empty() || cipher.is_empty() {
+        return cipher.to_string();
+    }
+    format!("{}-{}", header.encode(), cipher)
+}
+
+pub fn with_tag(cipher: &str, tag: Option<&mac::Tag>) -> String {
+    match tag {
+        Some(tag) if !cipher.is_empty() => format!("{}-{}", cipher, mac::encode(tag)),
+        _ => cipher.to_string(),
+    }
+}
+
+/// The ambiguity side of a raw cipher: header, ambiguity stream and tag as
+/// sections, while the DNA travels on its own.
+pub fn raw_ambig(header: &header::Header, ambig_vec: &[u8], tag: Option<&mac::Tag>) -> String {
+    let ambig: String = ambig_vec
+        .iter()
+        .map(|ambig| constants::DNA[ambig])
+        .collect();
+    with_tag(&with_header(header, &ambig), tag)
+}
+
+/// The cipher of raw DNA and its ambiguity side, as `raw_ambig` split it.
+/// Whitespace, FASTA wrapping and T for U are accepted in both.
+pub fn join_raw(dna: &str, ambig: &str, before: bool) -> String {
+    let dna = cipher_sections(dna).concat();
+    let mut sections = cipher_sections(ambig);
+    if dna.is_empty() && sections.concat().is_empty() {
+        return String::new();
+    }
+    // [AMBIG], [HEADER, AMBIG] or [HEADER, AMBIG, TAG]
+    let index = usize::from(sections.len() > 1);
+    sections[index] = match before {
+        true => format!("{}-{}", sections[index], dna),
+        false => format!("{}-{}", dna, sections[index]),
+    };
+    sections.join("-")
+}
+
+/// Pad length of a non-empty message: 1 to 3 units, each holding the count,
+/// so the decrypt side strips exactly what was added.
+fn pad_len(units: usize) -> usize {
+    3 - units % 3
+}
+
+pub fn utf8_to_binary(text: &str) -> Vec<u8> {
     let mut bin = text.as_bytes().to_vec();
-    while bin.len() % 3 != 0 {
-        bin.push(b' ');
+    if !bin.is_empty() {
+        let n = pad_len(bin.len());
+        bin.extend(std::iter::repeat_n(n as u8, n));
     }
     bin
 }
 
-pub fn utf16_to_binary(text: &Vec<u16>) -> Vec<u8> {
-    let mut bin: Vec<u8> = Vec::with_capacity(text.len() * 2);
-    for two_bytes in text.iter() {
-        let split = two_bytes.to_be_bytes();
-        bin.push(split[0]);
-        bin.push(split[1]);
+pub fn utf16_to_binary(text: &[u16]) -> Vec<u8> {
+    let mut units = text.to_vec();
+    if !units.is_empty() {
+        let n = pad_len(units.len());
+        units.extend(std::iter::repeat_n(n as u16, n));
     }
-    while (bin.len() / 2) % 3 != 0 {
-        bin.push(0b00100000);
-        bin.push(0b00000000);
+    units.iter().flat_map(|unit| unit.to_be_bytes()).collect()
+}
+
+/// `units` without the padding of `utf8_to_binary` or `utf16_to_binary`.
+/// Ciphers from before the padding carried its length end in spaces, which
+/// are kept.
+pub fn strip_padding<T: Copy + Into<u32>>(units: &[T]) -> &[T] {
+    match units.last().map(|&n| n.into() as usize) {
+        Some(n @ 1..=3)
+            if units.len() >= n
+                && units[units.len() - n..]
+                    .iter()
+                    .all(|&u| u.into() as usize == n) =>
+        {
+            &units[..units.len() - n]
+        }
+        _ => units,
     }
-    bin
 }
 
-pub fn binary_to_dna(bin: &Vec<u8>) -> Vec<char> {
+/// Big endian code units; a trailing odd byte is dropped.
+pub fn binary_to_utf16(bin: &[u8]) -> Vec<u16> {
+    bin.chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+pub fn binary_to_dna(bin: &[u8]) -> Vec<char> {
     let mut dna_vec: Vec<char> = Vec::with_capacity(bin.len() * 4);
     for byte in bin.iter() {
         for j in 0..4 {
@@ -70,35 +193,47 @@ pub fn binary_to_dna(bin: &Vec<u8>) -> Vec<char> {
     dna_vec
 }
 
-pub fn dna_to_binary(dna: &String) -> Vec<u8> {
-    let mut bytes: Vec<u8> = Vec::with_capacity(dna.len() / 4);
+/// Four nucleotides per byte; an incomplete last byte is dropped.
+pub fn dna_to_binary(dna: &str) -> Vec<u8> {
     let dna: Vec<char> = dna.chars().collect();
-    let offset = match dna.is_empty() {
-        true => 0usize,
-        false => 3,
-    };
-    for i in (0..dna.len() - offset).step_by(4) {
-        let mut b: u8 = 0;
-        for j in 0..4usize {
-            let c = match constants::DNA_REVERSE.get(&dna[i + j]) {
-                Some(&v) => v,
-                None => {
-                    println!("error in dna_to_bin, substituting with a 0");
-                    0u8
-                }
-            };
-            b = b | c << (6 - j * 2);
+    dna.chunks_exact(4)
+        .map(|nucleotides| {
+            nucleotides.iter().enumerate().fold(0u8, |b, (j, n)| {
+                // unknown nucleotides are reported by the decrypt diagnostics
+                let c = constants::DNA_REVERSE.get(n).copied().unwrap_or(0);
+                b | c << (6 - j * 2)
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CodonTable {
+    Standard,
+    Trifid,
+}
+
+impl CodonTable {
+    fn forward(&self) -> &'static phf::Map<&'static str, (char, u8)> {
+        match self {
+            CodonTable::Standard => &constants::ACID,
+            CodonTable::Trifid => &constants::TRIFID_ACID,
         }
-        // let b: u8 = (constants::DNA_REVERSE[&dna[i]] << 6)
-        //     | (constants::DNA_REVERSE[&dna[i + 1]] << 4)
-        //     | (constants::DNA_REVERSE[&dna[i + 2]] << 2)
-        //     | (constants::DNA_REVERSE[&dna[i + 3]]);
-        bytes.push(b);
     }
-    bytes
+
+    fn reverse(&self) -> &'static phf::Map<&'static str, &'static str> {
+        match self {
+            CodonTable::Standard => &constants::ACID_REVERSE,
+            CodonTable::Trifid => &constants::TRIFID_ACID_REVERSE,
+        }
+    }
+}
+
+pub fn dna_to_acids(dna_vec: &[char]) -> (Vec<char>, Vec<u8>) {
+    dna_to_acids_with(dna_vec, CodonTable::Standard)
 }
 
-pub fn dna_to_acids(dna_vec: &Vec<char>) -> (Vec<char>, Vec<u8>) {
+pub fn dna_to_acids_with(dna_vec: &[char], table: CodonTable) -> (Vec<char>, Vec<u8>) {
     let mut triplets: Vec<String> = Vec::with_capacity(dna_vec.len() / 3);
     let offset = dna_vec.len() % 3;
     for i in (0..dna_vec.len() - offset).step_by(3) {
@@ -111,12 +246,9 @@ pub fn dna_to_acids(dna_vec: &Vec<char>) -> (Vec<char>, Vec<u8>) {
     let mut acid_vec: Vec<char> = Vec::with_capacity(triplets.len());
     let mut ambig_vec: Vec<u8> = Vec::with_capacity(triplets.len());
     for acid in triplets.iter() {
-        let (c, ambig) = match constants::ACID.get(acid) {
+        let (c, ambig) = match table.forward().get(acid) {
             Some(&(c, ambig)) => (c, ambig),
-            None => {
-                println!("something went wrong; wrong ACID key");
-                ('X', 0) // shouldnt happen
-            }
+            None => ('X', 0), // only N, reported by the decrypt diagnostics
         };
         acid_vec.push(c);
         ambig_vec.push(ambig);
@@ -124,35 +256,45 @@ pub fn dna_to_acids(dna_vec: &Vec<char>) -> (Vec<char>, Vec<u8>) {
     (acid_vec, ambig_vec)
 }
 
-pub fn acids_to_dna(acid_vec: &Vec<char>, ambig_vec: &Vec<u8>) -> Vec<char> {
+pub fn acids_to_dna(acid_vec: &[char], ambig_vec: &[u8]) -> Vec<char> {
+    acids_to_dna_with(acid_vec, ambig_vec, CodonTable::Standard)
+}
+
+pub fn acids_to_dna_with(acid_vec: &[char], ambig_vec: &[u8], table: CodonTable) -> Vec<char> {
     let mut dna_vec: Vec<char> = Vec::with_capacity(acid_vec.len() * 3);
     for i in 0..acid_vec.len() {
         let key = format!("{}{}", acid_vec[i], ambig_vec[i]);
-        let v = match constants::ACID_REVERSE.get(&key) {
+        let v = match table.reverse().get(&key) {
             Some(&v) => v,
-            None => {
-                println!("something went wrong; wrong ACID key actodna");
-                "---" // shouldnt happen
-            }
+            None => "---", // reported by the decrypt diagnostics
         };
         dna_vec.append(&mut v.chars().collect::<Vec<char>>());
     }
     dna_vec
 }
 
+/// Nucleotide values (0-3) of the codon an acid and ambiguity digit stand for.
+pub fn codon(acid: char, ambig: u8) -> Option<[u8; 3]> {
+    let codon = constants::ACID_REVERSE.get(&format!("{}{}", acid, ambig))?;
+    let mut values = [0u8; 3];
+    for (value, c) in values.iter_mut().zip(codon.chars()) {
+        *value = constants::DNA_REVERSE[&c];
+    }
+    Some(values)
+}
+
 fn byte_fourth(index: usize, byte: u8) -> u8 {
     let c: u8 = 0b00000011;
-    c & (byte >> 6 - index)
+    c & (byte >> (6 - index))
 }
 
-pub fn dna_plus_ambig(dna: &String, ambig_vec: &Vec<u8>, before: bool) -> String {
+pub fn dna_plus_ambig(dna: &str, ambig_vec: &[u8], before: bool) -> String {
     let mut string = String::new();
     if dna.is_empty() && ambig_vec.is_empty() {
         return string;
     }
     if ambig_vec.len() * 3 != dna.len() {
-        println!("dna+ambig error: length mismatch");
-        return string;
+        return string; // not the output of one encryption
     }
     if !before {
         string += dna;
@@ -170,12 +312,10 @@ pub fn dna_plus_ambig(dna: &String, ambig_vec: &Vec<u8>, before: bool) -> String
     string
 }
 
-pub fn encrypt(key: &Vec<char>, text: &String, ambig_vec: &mut Vec<u8>) -> String {
+pub fn encrypt(key: &[char], text: &str, ambig_vec: &mut Vec<u8>) -> String {
     if key.len() != 25 {
-        println!("key format error");
-        panic!()
+        panic!("key format error")
     }
-    let text = text.to_uppercase();
     let key = key
         .iter()
         .collect::<String>()
@@ -183,6 +323,13 @@ pub fn encrypt(key: &Vec<char>, text: &String, ambig_vec: &mut Vec<u8>) -> Strin
         .chars()
         .collect::<Vec<char>>();
 
+    encrypt_digraphs(&key, &insert_fillers(text, ambig_vec))
+}
+
+/// Splits an acid string into digraphs: an X goes between doubled letters and
+/// after an odd last letter, and each filler is marked with a 4 in `ambig_vec`.
+pub fn insert_fillers(text: &str, ambig_vec: &mut Vec<u8>) -> String {
+    let text = text.to_uppercase();
     let mut text = text.chars().collect::<Vec<char>>();
     text.retain(|&c| !c.is_whitespace());
     if text.is_empty() {
@@ -212,6 +359,13 @@ pub fn encrypt(key: &Vec<char>, text: &String, ambig_vec: &mut Vec<u8>) -> Strin
         ambig_vec.push(4u8);
     }
 
+    chunks.iter().collect::<String>()
+}
+
+/// Encrypts an even-length text pair by pair without inserting fillers; a
+/// doubled pair falls into the same-row rule and stays invertible.
+pub fn encrypt_digraphs(key: &[char], text: &str) -> String {
+    let chunks = text.chars().collect::<Vec<char>>();
     let mut encrypted: Vec<char> = Vec::with_capacity(chunks.len());
 
     for k in (0..chunks.len()).step_by(2) {
@@ -224,7 +378,7 @@ pub fn encrypt(key: &Vec<char>, text: &String, ambig_vec: &mut Vec<u8>) -> Strin
             // same row
             encrypted.push(key[wrap_around(i1 / 5 * 5, i1 + 1)]); // modular arithmetic
             encrypted.push(key[wrap_around(i2 / 5 * 5, i2 + 1)]);
-        } else if (diff % 5) == 0 {
+        } else if diff.is_multiple_of(5) {
             // same column
             encrypted.push(key[(i1 + 5) % 25]);
             encrypted.push(key[(i2 + 5) % 25]);
@@ -238,18 +392,15 @@ pub fn encrypt(key: &Vec<char>, text: &String, ambig_vec: &mut Vec<u8>) -> Strin
     encrypted.iter().collect::<String>()
 }
 
-pub fn sanitize_acids(acids: &String, ambig: &Vec<u8>) -> String {
+pub fn sanitize_acids(acids: &str, ambig: &[u8]) -> String {
     let mut sanitized_acids = String::new();
     if acids.len() != ambig.len() {
-        println!("something went wrong; unsanitized_acids/ambig_vec length mismatch");
         return sanitized_acids;
     }
     let unsanitized_acids = acids.chars().collect::<Vec<char>>();
     for (i, byte) in ambig.iter().enumerate() {
         if *byte != 4u8 {
             sanitized_acids.push(unsanitized_acids[i]);
-        } else {
-            println!("added {}  at index {} \n", unsanitized_acids[i], i);
         }
     }
     sanitized_acids
@@ -259,10 +410,9 @@ pub fn sanitize_ambig(ambig: &mut Vec<u8>) {
     ambig.retain(|&byte| byte != 4u8);
 }
 
-pub fn decrypt(key: &String, text: &String) -> String {
+pub fn decrypt(key: &str, text: &str) -> String {
     if key.len() != 25 {
-        println!("key format error");
-        panic!()
+        panic!("key format error")
     }
     let text = text.to_uppercase();
     let key = key.to_uppercase().chars().collect::<Vec<char>>();
@@ -282,7 +432,7 @@ pub fn decrypt(key: &String, text: &String) -> String {
             // same row
             decrypted.push(key[wrap_around_l(i1 / 5 * 5, i1, 1) % 25]); // modular arithmetic
             decrypted.push(key[wrap_around_l(i2 / 5 * 5, i2, 1) % 25]);
-        } else if (diff % 5) == 0 {
+        } else if diff.is_multiple_of(5) {
             // same column
             decrypted.push(key[wrap_around_d(i1)]);
             decrypted.push(key[wrap_around_d(i2)]);
@@ -297,20 +447,51 @@ pub fn decrypt(key: &String, text: &String) -> String {
 }
 
 fn wrap_around(min: usize, value: usize) -> usize {
-    return min + (value % 5);
+    min + (value % 5)
 }
 fn wrap_around_l(min: usize, value: usize, minus: usize) -> usize {
     if value < minus || value - (minus % 5) < min {
-        return value + 5 - (minus % 5);
+        value + 5 - (minus % 5)
     } else {
-        return value - (minus % 5);
+        value - (minus % 5)
     }
 }
 
 fn wrap_around_d(value: usize) -> usize {
     if value < 5 {
-        return 20 + value;
+        20 + value
     } else {
-        return value - 5;
+        value - 5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{self, selftest};
+
+    #[test]
+    fn raw_streams_join_into_the_cipher() {
+        let key = generate_key_matrix("raw");
+        for (n, config) in selftest::configs().iter().enumerate() {
+            for &(case, text) in selftest::CORPUS {
+                let en = pipeline::encrypt(text, &key, config);
+                let (dna, ambig) = en.raw(config);
+                // Wrapped, with T for U, as written by the CLI
+                let wrapped: String = dna
+                    .replace('U', "T")
+                    .as_bytes()
+                    .chunks(7)
+                    .map(|line| String::from_utf8_lossy(line) + "\n")
+                    .collect();
+                assert_eq!(
+                    join_raw(&wrapped, &ambig, config.ambig_pos),
+                    en.cipher(config),
+                    "{} (configuration {})",
+                    case,
+                    n
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,115 @@
+use super::transposition;
+use itertools::Itertools;
+
+// ADFGX over the acid alphabet: every acid becomes its (row, column) pair in
+// the key matrix, the coordinate stream is transposed under a second keyword,
+// and the shuffled stream is paired back up into acids of the same matrix
+// (A/D/F/G/X is only the classical notation for the coordinates 0-4).
+
+pub const KEYWORD_ERROR: &str =
+    "the ADFGX transposition keyword needs at least two different letters";
+
+/// With fewer than two different letters the transposition leaves the
+/// coordinates in place, and the stage would send the acids as they are.
+pub fn valid_keyword(keyword: &str) -> bool {
+    keyword
+        .to_uppercase()
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .unique()
+        .count()
+        >= 2
+}
+
+fn coordinates(key: &[char], text: &str) -> Vec<usize> {
+    let mut coords: Vec<usize> = Vec::with_capacity(text.len() * 2);
+    for c in text.chars() {
+        let i = key.iter().position(|&k| k == c).unwrap(); // acids always come from the codon table
+        coords.push(i / 5);
+        coords.push(i % 5);
+    }
+    coords
+}
+
+fn pair_up(key: &[char], coords: &[usize]) -> String {
+    coords
+        .chunks(2)
+        .map(|pair| key[pair[0] * 5 + pair[1]])
+        .collect()
+}
+
+pub fn encrypt(key: &[char], text: &str, keyword: &str) -> String {
+    pair_up(
+        key,
+        &transposition::transpose(&coordinates(key, text), keyword),
+    )
+}
+
+pub fn decrypt(key: &[char], text: &str, keyword: &str) -> String {
+    pair_up(
+        key,
+        &transposition::untranspose(&coordinates(key, text), keyword),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::defaults::{Defaults, DefaultsError, StageName};
+    use crate::playfair::keygen;
+
+    const KEYWORDS: [&str; 4] = ["AB", "CARGO", "balloon", "ZEBRAS"];
+
+    #[test]
+    fn adfgx_round_trips() {
+        let key = keygen::random_key_matrix();
+        let acids: String = key.iter().cycle().step_by(7).take(60).collect();
+        for keyword in KEYWORDS {
+            for len in [0, 1, 2, 5, 13, 60] {
+                let text = &acids[..len];
+                let encrypted = encrypt(&key, text, keyword);
+                assert_eq!(encrypted.len(), len);
+                assert_eq!(
+                    decrypt(&key, &encrypted, keyword),
+                    text,
+                    "{} {}",
+                    keyword,
+                    len
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn adfgx_changes_the_acids() {
+        let key = keygen::random_key_matrix();
+        let acids: String = key.iter().cycle().step_by(7).take(60).collect();
+        for keyword in KEYWORDS {
+            assert_ne!(encrypt(&key, &acids, keyword), acids, "{}", keyword);
+        }
+    }
+
+    #[test]
+    fn keywords_need_two_different_letters() {
+        for keyword in KEYWORDS {
+            assert!(valid_keyword(keyword), "{}", keyword);
+        }
+        for keyword in ["", "X", "xX", "A-A 1"] {
+            assert!(!valid_keyword(keyword), "{}", keyword);
+        }
+        // A config file naming the stage without a keyword
+        let defaults = Defaults {
+            stage: Some(StageName::Adfgx),
+            ..Defaults::default()
+        };
+        assert!(matches!(
+            defaults.config(),
+            Err(DefaultsError::Invalid(KEYWORD_ERROR))
+        ));
+        let defaults = Defaults {
+            transposition: Some("CARGO".to_string()),
+            ..defaults
+        };
+        assert!(defaults.config().is_ok());
+    }
+}
@@ -1,4 +1,4 @@
-use phf;
+pub static ALPHABET: &str = "ABCDEFGHIKLMNOPQRSTUVWXYZ";
 
 pub static DNA: phf::Map<u8, char> = phf::phf_map! {
     0u8 => 'A',
@@ -150,3 +150,138 @@ pub static ACID_REVERSE: phf::Map<&'static str, &'static str> = phf::phf_map! {
     "Y0" => "UAU",
     "Z0" => "UAC",
 };
+
+// 27 symbols for Trifid: L and R each hand two codons to J and *
+pub static TRIFID_ACID: phf::Map<&'static str, (char, u8)> = phf::phf_map! {
+    "GCU" => ('A', 0),
+    "GCC" => ('A', 1),
+    "GCA" => ('A', 2),
+    "GCG" => ('A', 3),
+    "UAA" => ('B', 0),
+    "UAG" => ('B', 1),
+    "UGA" => ('B', 2),
+    "UGU" => ('C', 0),
+    "UGC" => ('C', 1),
+    "GAU" => ('D', 0),
+    "GAC" => ('D', 1),
+    "GAA" => ('E', 0),
+    "GAG" => ('E', 1),
+    "UUU" => ('F', 0),
+    "UUC" => ('F', 1),
+    "GGU" => ('G', 0),
+    "GGC" => ('G', 1),
+    "GGA" => ('G', 2),
+    "GGG" => ('G', 3),
+    "CAU" => ('H', 0),
+    "CAC" => ('H', 1),
+    "AUU" => ('I', 0),
+    "AUC" => ('I', 1),
+    "AUA" => ('I', 2),
+    "AAA" => ('K', 0),
+    "AAG" => ('K', 1),
+    "CUU" => ('L', 0),
+    "CUC" => ('L', 1),
+    "CUA" => ('J', 0),
+    "CUG" => ('J', 1),
+    "AUG" => ('M', 0),
+    "AAU" => ('N', 0),
+    "AAC" => ('N', 1),
+    "UUA" => ('O', 0),
+    "UUG" => ('O', 1),
+    "CCU" => ('P', 0),
+    "CCC" => ('P', 1),
+    "CCA" => ('P', 2),
+    "CCG" => ('P', 3),
+    "CAA" => ('Q', 0),
+    "CAG" => ('Q', 1),
+    "CGU" => ('R', 0),
+    "CGC" => ('R', 1),
+    "CGA" => ('*', 0),
+    "CGG" => ('*', 1),
+    "UCU" => ('S', 0),
+    "UCC" => ('S', 1),
+    "UCA" => ('S', 2),
+    "UCG" => ('S', 3),
+    "ACU" => ('T', 0),
+    "ACC" => ('T', 1),
+    "ACA" => ('T', 2),
+    "ACG" => ('T', 3),
+    "AGA" => ('U', 0),
+    "AGG" => ('U', 1),
+    "GUU" => ('V', 0),
+    "GUC" => ('V', 1),
+    "GUA" => ('V', 2),
+    "GUG" => ('V', 3),
+    "UGG" => ('W', 0),
+    "AGU" => ('X', 0),
+    "AGC" => ('X', 1),
+    "UAU" => ('Y', 0),
+    "UAC" => ('Z', 0),
+};
+
+pub static TRIFID_ACID_REVERSE: phf::Map<&'static str, &'static str> = phf::phf_map! {
+    "A0" => "GCU",
+    "A1" => "GCC",
+    "A2" => "GCA",
+    "A3" => "GCG",
+    "B0" => "UAA",
+    "B1" => "UAG",
+    "B2" => "UGA",
+    "C0" => "UGU",
+    "C1" => "UGC",
+    "D0" => "GAU",
+    "D1" => "GAC",
+    "E0" => "GAA",
+    "E1" => "GAG",
+    "F0" => "UUU",
+    "F1" => "UUC",
+    "G0" => "GGU",
+    "G1" => "GGC",
+    "G2" => "GGA",
+    "G3" => "GGG",
+    "H0" => "CAU",
+    "H1" => "CAC",
+    "I0" => "AUU",
+    "I1" => "AUC",
+    "I2" => "AUA",
+    "J0" => "CUA",
+    "J1" => "CUG",
+    "K0" => "AAA",
+    "K1" => "AAG",
+    "L0" => "CUU",
+    "L1" => "CUC",
+    "M0" => "AUG",
+    "N0" => "AAU",
+    "N1" => "AAC",
+    "O0" => "UUA",
+    "O1" => "UUG",
+    "P0" => "CCU",
+    "P1" => "CCC",
+    "P2" => "CCA",
+    "P3" => "CCG",
+    "Q0" => "CAA",
+    "Q1" => "CAG",
+    "R0" => "CGU",
+    "R1" => "CGC",
+    "S0" => "UCU",
+    "S1" => "UCC",
+    "S2" => "UCA",
+    "S3" => "UCG",
+    "T0" => "ACU",
+    "T1" => "ACC",
+    "T2" => "ACA",
+    "T3" => "ACG",
+    "U0" => "AGA",
+    "U1" => "AGG",
+    "V0" => "GUU",
+    "V1" => "GUC",
+    "V2" => "GUA",
+    "V3" => "GUG",
+    "W0" => "UGG",
+    "X0" => "AGU",
+    "X1" => "AGC",
+    "Y0" => "UAU",
+    "Z0" => "UAC",
+    "*0" => "CGA",
+    "*1" => "CGG",
+};
@@ -0,0 +1,144 @@
+// Bifid (5x5 square, two coordinates) and Trifid (3x3x3 cube, three
+// coordinates) fractionation. A period of 0 treats the whole text as one block.
+
+const TRIFID_EXTRA: [char; 2] = ['J', '*'];
+
+/// 27-symbol Trifid cube: the key matrix followed by the two symbols of the
+/// Trifid codon table that the 5x5 square has no room for.
+pub fn trifid_key(key: &[char]) -> Vec<char> {
+    let mut cube = key.to_vec();
+    cube.extend_from_slice(&TRIFID_EXTRA);
+    cube
+}
+
+pub fn bifid_encrypt(key: &[char], text: &str, period: usize) -> String {
+    fractionate(key, text, period, 5, 2)
+}
+
+pub fn bifid_decrypt(key: &[char], text: &str, period: usize) -> String {
+    unfractionate(key, text, period, 5, 2)
+}
+
+pub fn trifid_encrypt(key: &[char], text: &str, period: usize) -> String {
+    fractionate(&trifid_key(key), text, period, 3, 3)
+}
+
+pub fn trifid_decrypt(key: &[char], text: &str, period: usize) -> String {
+    unfractionate(&trifid_key(key), text, period, 3, 3)
+}
+
+fn coordinates(key: &[char], c: char, size: usize, dims: usize) -> Vec<usize> {
+    let mut index = key.iter().position(|&k| k == c).unwrap(); // acids always come from the codon table
+    let mut coords = vec![0usize; dims];
+    for d in (0..dims).rev() {
+        coords[d] = index % size;
+        index /= size;
+    }
+    coords
+}
+
+fn symbol(key: &[char], coords: &[usize], size: usize) -> char {
+    key[coords.iter().fold(0, |index, c| index * size + c)]
+}
+
+fn blocks(text: &str, period: usize) -> Vec<Vec<char>> {
+    let text: Vec<char> = text.chars().collect();
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let period = match period {
+        0 => text.len(),
+        p => p,
+    };
+    text.chunks(period).map(|c| c.to_vec()).collect()
+}
+
+fn fractionate(key: &[char], text: &str, period: usize, size: usize, dims: usize) -> String {
+    let mut encrypted = String::with_capacity(text.len());
+    for block in blocks(text, period) {
+        let coords: Vec<Vec<usize>> = block
+            .iter()
+            .map(|&c| coordinates(key, c, size, dims))
+            .collect();
+        // write each coordinate as a row, then read the rows off in groups
+        let mut stream: Vec<usize> = Vec::with_capacity(block.len() * dims);
+        for d in 0..dims {
+            stream.extend(coords.iter().map(|c| c[d]));
+        }
+        for group in stream.chunks(dims) {
+            encrypted.push(symbol(key, group, size));
+        }
+    }
+    encrypted
+}
+
+fn unfractionate(key: &[char], text: &str, period: usize, size: usize, dims: usize) -> String {
+    let mut decrypted = String::with_capacity(text.len());
+    for block in blocks(text, period) {
+        let stream: Vec<usize> = block
+            .iter()
+            .flat_map(|&c| coordinates(key, c, size, dims))
+            .collect();
+        for i in 0..block.len() {
+            let coords: Vec<usize> = (0..dims).map(|d| stream[d * block.len() + i]).collect();
+            decrypted.push(symbol(key, &coords, size));
+        }
+    }
+    decrypted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playfair::{self, keygen};
+
+    /// Every symbol of `alphabet` three times, interleaved with the letters of
+    /// a random key.
+    fn text(alphabet: &[char]) -> String {
+        let order = keygen::random_key_matrix();
+        alphabet
+            .iter()
+            .cycle()
+            .take(alphabet.len() * 3)
+            .zip(order.iter().cycle())
+            .flat_map(|(&a, &b)| [a, b])
+            .collect()
+    }
+
+    #[test]
+    fn bifid_matches_the_textbook_example() {
+        let key: Vec<char> = "BGWKZQPNDSIOAXEFCLUMTHYVR".chars().collect();
+        assert_eq!(bifid_encrypt(&key, "FLEEATONCE", 0), "UAEOLWRINS");
+        assert_eq!(bifid_decrypt(&key, "UAEOLWRINS", 0), "FLEEATONCE");
+    }
+
+    #[test]
+    fn bifid_and_trifid_round_trip() {
+        let key = keygen::random_key_matrix();
+        let bifid_text = text(&key);
+        let trifid_text = text(&trifid_key(&key));
+        for period in [0, 1, 2, 5, 7, bifid_text.len() + 1] {
+            let encrypted = bifid_encrypt(&key, &bifid_text, period);
+            assert_eq!(encrypted.len(), bifid_text.len());
+            assert_eq!(
+                bifid_decrypt(&key, &encrypted, period),
+                bifid_text,
+                "{}",
+                period
+            );
+            let encrypted = trifid_encrypt(&key, &trifid_text, period);
+            assert_eq!(encrypted.len(), trifid_text.len());
+            assert_eq!(
+                trifid_decrypt(&key, &encrypted, period),
+                trifid_text,
+                "{}",
+                period
+            );
+        }
+        assert_eq!(bifid_encrypt(&key, "", 5), "");
+        assert_eq!(
+            trifid_decrypt(&playfair::generate_key_matrix("x"), "", 0),
+            ""
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use super::kdf::{KdfParams, SALT_LEN};
+use super::nonce::{Nonce, NONCE_LEN};
+
+const FLAG_KDF: u8 = 0b0000_0001;
+const FLAG_MASK_AMBIG: u8 = 0b0000_0010;
+const FLAG_NONCE: u8 = 0b0000_0100;
+const FLAG_MAC: u8 = 0b0000_1000;
+const KNOWN_FLAGS: u8 = FLAG_KDF | FLAG_MASK_AMBIG | FLAG_NONCE | FLAG_MAC;
+
+/// Cipher parameters the decrypt side needs, carried as a leading
+/// nucleotide section (`HEADER-AMBIG-DNA` / `HEADER-DNA-AMBIG`). With `mac`
+/// set, an authentication tag follows as a last section.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Header {
+    pub kdf: Option<KdfParams>,
+    pub mask_ambig: bool,
+    pub nonce: Option<Nonce>,
+    pub mac: bool,
+}
+
+impl Header {
+    pub fn is_empty(&self) -> bool {
+        self.kdf.is_none() && !self.mask_ambig && self.nonce.is_none() && !self.mac
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut flags = 0u8;
+        let mut bytes: Vec<u8> = Vec::new();
+        if let Some(kdf) = &self.kdf {
+            flags |= FLAG_KDF;
+            bytes.extend_from_slice(&kdf.m_cost.to_be_bytes());
+            bytes.extend_from_slice(&kdf.t_cost.to_be_bytes());
+            bytes.extend_from_slice(&kdf.p_cost.to_be_bytes());
+            bytes.extend_from_slice(&kdf.salt);
+        }
+        if self.mask_ambig {
+            flags |= FLAG_MASK_AMBIG;
+        }
+        if let Some(nonce) = &self.nonce {
+            flags |= FLAG_NONCE;
+            bytes.extend_from_slice(nonce);
+        }
+        if self.mac {
+            flags |= FLAG_MAC;
+        }
+        bytes.insert(0, flags);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Header> {
+        let (&flags, mut rest) = bytes.split_first()?;
+        let mut header = Header::default();
+        if flags & FLAG_KDF != 0 {
+            let (params, tail) = rest.split_at_checked(12 + SALT_LEN)?;
+            let word = |i: usize| u32::from_be_bytes(params[i * 4..i * 4 + 4].try_into().unwrap());
+            let kdf = KdfParams {
+                m_cost: word(0),
+                t_cost: word(1),
+                p_cost: word(2),
+                salt: params[12..].try_into().unwrap(),
+            };
+            if !kdf.within_limits() {
+                return None;
+            }
+            header.kdf = Some(kdf);
+            rest = tail;
+        }
+        header.mask_ambig = flags & FLAG_MASK_AMBIG != 0;
+        if flags & FLAG_NONCE != 0 {
+            let (nonce, tail) = rest.split_at_checked(NONCE_LEN)?;
+            header.nonce = Some(nonce.try_into().unwrap());
+            rest = tail;
+        }
+        header.mac = flags & FLAG_MAC != 0;
+        if flags & !KNOWN_FLAGS != 0 || !rest.is_empty() {
+            return None;
+        }
+        Some(header)
+    }
+
+    /// Nucleotide form of the header, four bases per byte.
+    pub fn encode(&self) -> String {
+        super::binary_to_dna(&self.to_bytes()).iter().collect()
+    }
+
+    pub fn decode(dna: &str) -> Option<Header> {
+        if dna.is_empty() || !dna.len().is_multiple_of(4) {
+            return None;
+        }
+        Header::from_bytes(&super::dna_to_binary(dna))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{self, Config, Status};
+    use crate::playfair::kdf::{MAX_M_COST, MAX_P_COST, MAX_T_COST};
+    use crate::playfair::{self, nonce};
+
+    #[test]
+    fn headers_decode_to_what_was_encoded() {
+        for kdf in [None, Some(KdfParams::random())] {
+            for nonce in [None, Some(nonce::random())] {
+                for (mask_ambig, mac) in
+                    [(false, false), (true, false), (false, true), (true, true)]
+                {
+                    let header = Header {
+                        kdf: kdf.clone(),
+                        mask_ambig,
+                        nonce,
+                        mac,
+                    };
+                    let dna = header.encode();
+                    assert!(dna.chars().all(|c| "ACGU".contains(c)), "{}", dna);
+                    assert_eq!(Header::decode(&dna), Some(header));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn damaged_headers_do_not_decode() {
+        let header = Header {
+            kdf: Some(KdfParams::random()),
+            nonce: Some(nonce::random()),
+            ..Header::default()
+        };
+        let bytes = header.to_bytes();
+        // Cut short, with a byte to spare, and with a flag no version sets
+        assert_eq!(Header::from_bytes(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(Header::from_bytes(&[&bytes[..], &[0]].concat()), None);
+        assert_eq!(Header::from_bytes(&[0b1000_0000]), None);
+        assert_eq!(Header::from_bytes(&[]), None);
+        assert_eq!(Header::decode(&header.encode()[1..]), None);
+    }
+
+    #[test]
+    fn huge_kdf_costs_make_the_cipher_malformed() {
+        let key = playfair::generate_key_matrix("costs");
+        let config = Config::default();
+        let cipher = pipeline::encrypt("hello", &key, &config).cipher(&config);
+        for (m_cost, t_cost, p_cost) in [
+            (u32::MAX, 2, 1),
+            (MAX_M_COST + 1, 2, 1),
+            (19 * 1024, MAX_T_COST + 1, 1),
+            (19 * 1024, 2, MAX_P_COST + 1),
+        ] {
+            let header = Header {
+                kdf: Some(KdfParams {
+                    m_cost,
+                    t_cost,
+                    p_cost,
+                    ..KdfParams::default()
+                }),
+                ..Header::default()
+            };
+            assert_eq!(Header::decode(&header.encode()), None);
+            let crafted = format!("{}-{}", header.encode(), cipher);
+            let de = pipeline::decrypt(&crafted, &key, &config);
+            assert!(matches!(de.status, Status::Malformed(_)), "{:?}", de.status);
+        }
+    }
+
+    #[test]
+    fn kdf_costs_up_to_the_caps_are_read() {
+        let kdf = KdfParams {
+            m_cost: MAX_M_COST,
+            t_cost: MAX_T_COST,
+            p_cost: MAX_P_COST,
+            ..KdfParams::random()
+        };
+        let header = Header {
+            kdf: Some(kdf.clone()),
+            ..Header::default()
+        };
+        assert_eq!(Header::decode(&header.encode()).unwrap().kdf, Some(kdf));
+    }
+}
@@ -0,0 +1,62 @@
+use super::keygen;
+use argon2::{Algorithm, Argon2, Params, Version};
+
+pub const SALT_LEN: usize = 16;
+/// Upper bounds for costs read from a cipher header, four times the defaults,
+/// so that a crafted cipher cannot stall the decrypt side. Headers above them
+/// are rejected before anything is derived.
+pub const MAX_M_COST: u32 = 4 * 19 * 1024; // 76 MiB
+pub const MAX_T_COST: u32 = 4 * 2;
+pub const MAX_P_COST: u32 = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KdfParams {
+    pub m_cost: u32, // KiB
+    pub t_cost: u32,
+    pub p_cost: u32,
+    pub salt: [u8; SALT_LEN],
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: 19 * 1024,
+            t_cost: 2,
+            p_cost: 1,
+            salt: [0u8; SALT_LEN],
+        }
+    }
+}
+
+impl KdfParams {
+    /// Default Argon2id costs with a fresh random salt.
+    pub fn random() -> Self {
+        let mut params = Self::default();
+        getrandom::getrandom(&mut params.salt).expect("OS random number generator unavailable");
+        params
+    }
+
+    pub fn within_limits(&self) -> bool {
+        self.m_cost <= MAX_M_COST && self.t_cost <= MAX_T_COST && self.p_cost <= MAX_P_COST
+    }
+}
+
+/// Stretches a passphrase with Argon2id and maps the output to a uniformly
+/// distributed key matrix.
+pub fn derive_key_matrix(passphrase: &str, params: &KdfParams) -> Result<Vec<char>, argon2::Error> {
+    let mut output = [0u8; 64];
+    let argon = Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(
+            params.m_cost,
+            params.t_cost,
+            params.p_cost,
+            Some(output.len()),
+        )?,
+    );
+    argon.hash_password_into(passphrase.as_bytes(), &params.salt, &mut output)?;
+
+    // all four candidates landing in the rejected zone is below 2^-170
+    Ok(keygen::key_from_bytes(&output))
+}
@@ -0,0 +1,215 @@
+use super::constants;
+use std::fmt;
+
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const INDEX_BYTES: usize = 11; // 25! < 2^84
+const KEY_STRING_LEN: usize = (INDEX_BYTES + 4) * 8 / 5;
+
+#[derive(Debug, PartialEq)]
+pub enum KeyStringError {
+    Length(usize),
+    Symbol(char),
+    Checksum,
+    Range,
+}
+
+impl fmt::Display for KeyStringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyStringError::Length(len) => write!(
+                f,
+                "key string has {} symbols, expected {}",
+                len, KEY_STRING_LEN
+            ),
+            KeyStringError::Symbol(c) => write!(f, "'{}' is not a key string symbol", c),
+            KeyStringError::Checksum => write!(f, "key string checksum mismatch"),
+            KeyStringError::Range => write!(f, "key string is not a valid permutation index"),
+        }
+    }
+}
+
+impl std::error::Error for KeyStringError {}
+
+fn factorial(n: usize) -> u128 {
+    (1..=n as u128).product()
+}
+
+/// Number of distinct 5x5 matrices (25!).
+pub fn key_space() -> u128 {
+    factorial(25)
+}
+
+/// Draws a uniformly random key matrix from the OS CSPRNG.
+pub fn random_key_matrix() -> Vec<char> {
+    let space = key_space();
+    let zone = space * (u128::MAX / space);
+    loop {
+        let mut bytes = [0u8; 16];
+        getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+        let value = u128::from_be_bytes(bytes);
+        if value < zone {
+            return key_from_index(value % space).unwrap();
+        }
+    }
+}
+
+/// Maps uniformly random bytes to a uniformly random key matrix. Every 16
+/// bytes make one candidate index; candidates from the zone that would bias
+/// the modulo are skipped, and the first is used if all of them are.
+pub fn key_from_bytes(bytes: &[u8]) -> Vec<char> {
+    let space = key_space();
+    let zone = space * (u128::MAX / space);
+    let candidates: Vec<u128> = bytes
+        .chunks_exact(16)
+        .map(|c| u128::from_be_bytes(c.try_into().unwrap()))
+        .collect();
+    let index = match candidates.iter().find(|&&c| c < zone) {
+        Some(&c) => c,
+        None => candidates[0],
+    };
+    key_from_index(index % space).unwrap()
+}
+
+/// Lehmer rank of a key matrix, in `0..25!`.
+pub fn permutation_index(key: &[char]) -> Option<u128> {
+    let mut remaining: Vec<char> = constants::ALPHABET.chars().collect();
+    if key.len() != remaining.len() {
+        return None;
+    }
+    let mut index = 0u128;
+    for (i, c) in key.iter().enumerate() {
+        let pos = remaining.iter().position(|r| r == c)?;
+        remaining.remove(pos);
+        index += pos as u128 * factorial(24 - i);
+    }
+    Some(index)
+}
+
+/// Inverse of `permutation_index`.
+pub fn key_from_index(mut index: u128) -> Option<Vec<char>> {
+    if index >= key_space() {
+        return None;
+    }
+    let mut remaining: Vec<char> = constants::ALPHABET.chars().collect();
+    let mut key: Vec<char> = Vec::with_capacity(remaining.len());
+    for i in (0..remaining.len()).rev() {
+        let f = factorial(i);
+        key.push(remaining.remove((index / f) as usize));
+        index %= f;
+    }
+    Some(key)
+}
+
+/// Encodes a key matrix as a base32 permutation index followed by its CRC-32.
+pub fn export_key(key: &[char]) -> Option<String> {
+    let index = permutation_index(key)?;
+    let mut bytes = index.to_be_bytes()[16 - INDEX_BYTES..].to_vec();
+    let crc = crc32fast::hash(&bytes);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+
+    let mut string = String::with_capacity(KEY_STRING_LEN);
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for byte in bytes.iter() {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            string.push(BASE32[((buffer >> bits) & 0b11111) as usize] as char);
+        }
+    }
+    Some(string)
+}
+
+/// Parses a key string produced by `export_key`. Whitespace and dashes are ignored.
+pub fn import_key(string: &str) -> Result<Vec<char>, KeyStringError> {
+    let symbols: Vec<char> = string
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if symbols.len() != KEY_STRING_LEN {
+        return Err(KeyStringError::Length(symbols.len()));
+    }
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(INDEX_BYTES + 4);
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for c in symbols.iter() {
+        let value = match BASE32.iter().position(|&b| b as char == *c) {
+            Some(v) => v as u32,
+            None => return Err(KeyStringError::Symbol(*c)),
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    let (index_bytes, crc) = bytes.split_at(INDEX_BYTES);
+    if crc32fast::hash(index_bytes).to_be_bytes() != crc {
+        return Err(KeyStringError::Checksum);
+    }
+    let mut padded = [0u8; 16];
+    padded[16 - INDEX_BYTES..].copy_from_slice(index_bytes);
+    key_from_index(u128::from_be_bytes(padded)).ok_or(KeyStringError::Range)
+}
+
+/// Key field contents: a shared key string if it checks out, a keyword otherwise.
+pub fn resolve_key(input: &str) -> Vec<char> {
+    match import_key(input) {
+        Ok(key) => key,
+        Err(_) => super::generate_key_matrix(input),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_keys_import_back() {
+        let keys = [
+            random_key_matrix(),
+            crate::playfair::generate_key_matrix("playfair example"),
+            key_from_index(0).unwrap(),
+            key_from_index(key_space() - 1).unwrap(),
+        ];
+        for key in keys {
+            let string = export_key(&key).unwrap();
+            assert_eq!(string.len(), KEY_STRING_LEN);
+            assert_eq!(import_key(&string), Ok(key.clone()));
+            // As read aloud or copied: grouped and in lower case
+            let grouped = string
+                .to_lowercase()
+                .as_bytes()
+                .chunks(4)
+                .map(|group| String::from_utf8_lossy(group).into_owned())
+                .collect::<Vec<String>>()
+                .join("-");
+            assert_eq!(import_key(&grouped), Ok(key));
+        }
+    }
+
+    #[test]
+    fn a_changed_symbol_fails_the_checksum() {
+        let string = export_key(&random_key_matrix()).unwrap();
+        for i in 0..string.len() {
+            let mut symbols: Vec<char> = string.chars().collect();
+            symbols[i] = if symbols[i] == 'A' { 'B' } else { 'A' };
+            let changed: String = symbols.into_iter().collect();
+            assert_eq!(
+                import_key(&changed),
+                Err(KeyStringError::Checksum),
+                "{}",
+                changed
+            );
+        }
+        assert_eq!(
+            import_key(&string[1..]),
+            Err(KeyStringError::Length(KEY_STRING_LEN - 1))
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+// Authentication tag over the plaintext binary and the cipher parameters,
+// HMAC-SHA256 truncated to 15 bytes so that it is exactly 20 codons long.
+// It is sent as the last nucleotide section and flagged in the header.
+
+pub const TAG_LEN: usize = 15;
+
+pub type Tag = [u8; TAG_LEN];
+
+fn mac_key(key: &[char]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"playfair-dna mac key");
+    hasher.update(key.iter().collect::<String>().as_bytes());
+    hasher.finalize().into()
+}
+
+fn hmac(key: &[char], parameters: &[u8], binary: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&mac_key(key)).unwrap();
+    mac.update(&(parameters.len() as u32).to_be_bytes());
+    mac.update(parameters);
+    mac.update(binary);
+    mac
+}
+
+/// `parameters` should cover everything that changes how the cipher is read,
+/// the header included.
+pub fn tag(key: &[char], parameters: &[u8], binary: &[u8]) -> Tag {
+    hmac(key, parameters, binary).finalize().into_bytes()[..TAG_LEN]
+        .try_into()
+        .unwrap()
+}
+
+/// Constant-time comparison against a received tag.
+pub fn verify(key: &[char], parameters: &[u8], binary: &[u8], received: &Tag) -> bool {
+    hmac(key, parameters, binary)
+        .verify_truncated_left(received)
+        .is_ok()
+}
+
+pub fn encode(tag: &Tag) -> String {
+    super::binary_to_dna(tag).iter().collect()
+}
+
+pub fn decode(dna: &str) -> Option<Tag> {
+    if dna.len() != TAG_LEN * 4 {
+        return None;
+    }
+    super::dna_to_binary(dna).try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{self, Config, Status};
+    use crate::playfair;
+
+    /// `cipher` with the base at `i` replaced by another one.
+    fn flip(cipher: &str, i: usize) -> String {
+        let mut bases: Vec<char> = cipher.chars().collect();
+        bases[i] = match bases[i] {
+            'A' => 'C',
+            'C' => 'G',
+            'G' => 'U',
+            _ => 'A',
+        };
+        bases.into_iter().collect()
+    }
+
+    #[test]
+    fn tags_verify_and_encode() {
+        let key = playfair::generate_key_matrix("tag");
+        let tag = tag(&key, b"settings", b"binary");
+        assert!(verify(&key, b"settings", b"binary", &tag));
+        assert!(!verify(&key, b"settings", b"binarY", &tag));
+        assert!(!verify(&key, b"Settings", b"binary", &tag));
+        let other = playfair::generate_key_matrix("other");
+        assert!(!verify(&other, b"settings", b"binary", &tag));
+        assert_eq!(encode(&tag).len(), TAG_LEN * 4);
+        assert_eq!(decode(&encode(&tag)), Some(tag));
+        assert_eq!(decode(&encode(&tag)[4..]), None);
+    }
+
+    #[test]
+    fn tampered_ciphers_fail_authentication() {
+        let key = playfair::generate_key_matrix("tag");
+        let config = Config {
+            mac: true,
+            ..Config::default()
+        };
+        let cipher = pipeline::encrypt("attack at dawn", &key, &config).cipher(&config);
+        let de = pipeline::decrypt(&cipher, &key, &config);
+        assert_eq!(de.status, Status::Verified);
+        assert_eq!(de.plain_text, "attack at dawn");
+
+        let other = playfair::generate_key_matrix("dusk");
+        let de = pipeline::decrypt(&cipher, &other, &config);
+        assert_eq!(de.status, Status::BadTag);
+
+        // A base of the tag, and the first of the last DNA codon; a third
+        // base may only swap a codon for a synonym of the same acid
+        let last = cipher.rfind('-').unwrap();
+        for i in [cipher.len() - 1, last - 3] {
+            let de = pipeline::decrypt(&flip(&cipher, i), &key, &config);
+            assert_eq!(de.status, Status::BadTag, "base {}", i);
+            assert_ne!(de.plain_text, "attack at dawn");
+        }
+    }
+}
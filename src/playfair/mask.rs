@@ -0,0 +1,80 @@
+use super::nonce::Nonce;
+use sha2::{Digest, Sha256};
+
+// The ambiguity stream is sent next to the DNA and would otherwise show where
+// fillers went (4/N) and which synonymous codon each acid came from. In masked
+// mode every digit, fillers included, is shifted mod 5 by a keystream that
+// SHA-256 derives from the key matrix and the message nonce. Without the
+// nonce every message under one key would share the keystream, and two
+// masked streams subtracted from each other would cancel it.
+
+const SYMBOLS: u8 = 5;
+
+/// Digits in `0..5`; bytes of 255 are skipped so that every digit is equally likely.
+/// A masked cipher without a nonce in its header hashes the key alone.
+pub fn keystream(key: &[char], nonce: Option<&Nonce>, len: usize) -> Vec<u8> {
+    let key: String = key.iter().collect();
+    let mut stream: Vec<u8> = Vec::with_capacity(len);
+    let mut block = 0u32;
+    while stream.len() < len {
+        let mut hasher = Sha256::new();
+        hasher.update(b"playfair-dna ambiguity mask");
+        hasher.update(key.as_bytes());
+        if let Some(nonce) = nonce {
+            hasher.update(nonce);
+        }
+        hasher.update(block.to_be_bytes());
+        for byte in hasher.finalize() {
+            if byte < 255 && stream.len() < len {
+                stream.push(byte % SYMBOLS);
+            }
+        }
+        block += 1;
+    }
+    stream
+}
+
+pub fn mask(key: &[char], nonce: Option<&Nonce>, ambig_vec: &[u8]) -> Vec<u8> {
+    ambig_vec
+        .iter()
+        .zip(keystream(key, nonce, ambig_vec.len()))
+        .map(|(a, k)| (a + k) % SYMBOLS)
+        .collect()
+}
+
+pub fn unmask(key: &[char], nonce: Option<&Nonce>, ambig_vec: &[u8]) -> Vec<u8> {
+    ambig_vec
+        .iter()
+        .zip(keystream(key, nonce, ambig_vec.len()))
+        .map(|(a, k)| (a + SYMBOLS - k) % SYMBOLS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{self, defaults::Defaults};
+    use crate::playfair;
+
+    #[test]
+    fn messages_under_one_key_get_different_masks() {
+        let key = playfair::generate_key_matrix("mask");
+        let masked = Defaults {
+            mask_ambig: Some(true),
+            ..Defaults::default()
+        };
+        let masks: Vec<Vec<u8>> = (0..2)
+            .map(|_| {
+                let config = masked.config().unwrap();
+                assert!(config.nonce.is_some());
+                let en = pipeline::encrypt("the same text twice", &key, &config);
+                en.cipher_ambig
+                    .iter()
+                    .zip(en.ambig_vec.iter())
+                    .map(|(c, a)| (c + SYMBOLS - a) % SYMBOLS)
+                    .collect()
+            })
+            .collect();
+        assert_ne!(masks[0], masks[1]);
+    }
+}
@@ -0,0 +1,25 @@
+use super::keygen;
+use sha2::{Digest, Sha256};
+
+// Per-message nonce. The key matrix for a message is derived from the master
+// matrix and a fresh random nonce sent in the header, so the same plaintext
+// encrypts differently every time.
+
+pub const NONCE_LEN: usize = 12;
+
+pub type Nonce = [u8; NONCE_LEN];
+
+pub fn random() -> Nonce {
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut nonce).expect("OS random number generator unavailable");
+    nonce
+}
+
+/// Matrix for one message, a hash-derived permutation of master key and nonce.
+pub fn message_key(master: &[char], nonce: &Nonce) -> Vec<char> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"playfair-dna message key");
+    hasher.update(master.iter().collect::<String>().as_bytes());
+    hasher.update(nonce);
+    keygen::key_from_bytes(&hasher.finalize())
+}
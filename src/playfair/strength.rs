@@ -0,0 +1,251 @@
+use super::{constants, keygen};
+
+// Keywords and passphrases are typed by people, so a character is credited
+// with about as much entropy as a letter of English prose, not log2 of the
+// alphabet size.
+const BITS_PER_CHAR: f64 = 2.0;
+// A matrix this close to a shift of the default one is flagged as near-identity.
+const NEAR_IDENTITY: usize = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rating {
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl Rating {
+    pub fn string(&self) -> &str {
+        match self {
+            Rating::Weak => "Weak",
+            Rating::Fair => "Fair",
+            Rating::Strong => "Strong",
+        }
+    }
+}
+
+/// How a matrix lines up with the default alphabet matrix. Grid shifts
+/// encrypt exactly like the default matrix; rotations of the alphabet come
+/// from single-letter keywords.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resemblance {
+    Identity,
+    Rotation(usize),
+    GridShift { rows: usize, columns: usize },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyStrength {
+    /// Cells placed by the keyword, i.e. everything before the alphabetical tail.
+    pub keyword_cells: usize,
+    /// Length of the tail that is left in alphabetical order.
+    pub alphabetical_cells: usize,
+    pub entropy_bits: f64,
+    /// Closest shift of the default matrix and the number of cells that differ
+    /// from it, if that number is small.
+    pub resemblance: Option<(Resemblance, usize)>,
+}
+
+/// log2(25!), the entropy of a uniformly random matrix.
+pub fn max_entropy_bits() -> f64 {
+    (2..=25).map(|n| (n as f64).log2()).sum()
+}
+
+fn alphabetical_tail(key: &[char]) -> usize {
+    let order: Vec<Option<usize>> = key
+        .iter()
+        .map(|c| constants::ALPHABET.chars().position(|a| a == *c))
+        .collect();
+    match order
+        .windows(2)
+        .rposition(|w| w[0] >= w[1] || w[0].is_none())
+    {
+        Some(i) => key.len() - i - 1,
+        None => key.len(),
+    }
+}
+
+fn resemblance(key: &[char]) -> Option<(Resemblance, usize)> {
+    let default: Vec<char> = constants::ALPHABET.chars().collect();
+    if key.len() != default.len() {
+        return None;
+    }
+    let displaced =
+        |cell: &dyn Fn(usize) -> usize| (0..25).filter(|&i| key[i] != default[cell(i)]).count();
+
+    let mut best = (Resemblance::Identity, displaced(&|i| i));
+    for shift in 1..25 {
+        let count = displaced(&|i| (i + shift) % 25);
+        if count < best.1 {
+            best = (Resemblance::Rotation(shift), count);
+        }
+    }
+    for rows in 0..5 {
+        for columns in 0..5 {
+            let count = displaced(&|i| (i / 5 + rows) % 5 * 5 + (i % 5 + columns) % 5);
+            if count < best.1 {
+                best = (Resemblance::GridShift { rows, columns }, count);
+            }
+        }
+    }
+    match best.1 <= NEAR_IDENTITY {
+        true => Some(best),
+        false => None,
+    }
+}
+
+fn analyze_matrix(key: &[char], entropy_bits: f64) -> KeyStrength {
+    let alphabetical_cells = alphabetical_tail(key);
+    KeyStrength {
+        keyword_cells: key.len() - alphabetical_cells,
+        alphabetical_cells,
+        entropy_bits: entropy_bits.min(max_entropy_bits()),
+        resemblance: resemblance(key),
+    }
+}
+
+/// Analyzes the contents of a key field: a shared key string is worth a full
+/// random matrix, a keyword only what its letters add before the alphabetical tail.
+pub fn analyze_key(input: &str) -> KeyStrength {
+    if let Ok(key) = keygen::import_key(input) {
+        return analyze_matrix(&key, max_entropy_bits());
+    }
+    let key = super::generate_key_matrix(input);
+    let cells = key.len() - alphabetical_tail(&key);
+    let bits = (0..cells).fold(0.0, |bits, i| {
+        bits + ((25 - i) as f64).log2().min(BITS_PER_CHAR)
+    });
+    analyze_matrix(&key, bits)
+}
+
+/// Analyzes a passphrase and the matrix Argon2 derived from it. The KDF slows
+/// down guessing but adds no entropy, so the estimate comes from the passphrase.
+pub fn analyze_passphrase(passphrase: &str, key: &[char]) -> KeyStrength {
+    analyze_matrix(key, passphrase.chars().count() as f64 * BITS_PER_CHAR)
+}
+
+impl KeyStrength {
+    pub fn rating(&self) -> Rating {
+        if self.resemblance.is_some() || self.entropy_bits < 24.0 {
+            Rating::Weak
+        } else if self.entropy_bits < 48.0 {
+            Rating::Fair
+        } else {
+            Rating::Strong
+        }
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = Vec::new();
+        match self.resemblance {
+            Some((Resemblance::Identity, 0)) => {
+                warnings.push("the matrix is the plain alphabet".to_string())
+            }
+            Some((Resemblance::Identity, displaced)) => warnings.push(format!(
+                "the matrix differs from the plain alphabet in only {} cells",
+                displaced
+            )),
+            Some((Resemblance::Rotation(shift), displaced)) => warnings.push(format!(
+                "the matrix is the alphabet rotated by {} ({} cells differ)",
+                shift, displaced
+            )),
+            Some((Resemblance::GridShift { rows, columns }, displaced)) => {
+                warnings.push(format!(
+                    "the matrix is the default one shifted by {} rows and {} columns ({} cells differ), \
+                     which encrypts the same way",
+                    rows, columns, displaced
+                ))
+            }
+            None => {}
+        }
+        if self.alphabetical_cells > 12 {
+            warnings.push(format!(
+                "{} of 25 cells are left in alphabetical order",
+                self.alphabetical_cells
+            ));
+        }
+        if self.entropy_bits < 24.0 {
+            warnings.push(format!(
+                "only about {:.0} bits of entropy - use a longer key or generate one",
+                self.entropy_bits
+            ));
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playfair::generate_key_matrix;
+
+    // Reversed keywords leave no letter where any shift of the alphabet puts it
+    const FAIR_KEYWORD: &str = "ZYXWVUTSRQPO";
+
+    #[test]
+    fn keyword_ratings_step_at_24_and_48_bits() {
+        let weak = analyze_key(&FAIR_KEYWORD[..11]);
+        assert_eq!((weak.keyword_cells, weak.resemblance), (11, None));
+        assert_eq!(weak.entropy_bits, 22.0);
+        assert_eq!(weak.rating(), Rating::Weak);
+
+        let fair = analyze_key(FAIR_KEYWORD);
+        assert_eq!((fair.keyword_cells, fair.resemblance), (12, None));
+        assert_eq!(fair.entropy_bits, 24.0);
+        assert_eq!(fair.rating(), Rating::Fair);
+
+        let key = generate_key_matrix(FAIR_KEYWORD);
+        let string = keygen::export_key(&key).unwrap();
+        let strong = analyze_key(&string);
+        assert_eq!(strong.entropy_bits, max_entropy_bits());
+        assert_eq!(strong.rating(), Rating::Strong);
+        assert!(strong.warnings().iter().any(|w| w.contains("alphabetical")));
+    }
+
+    #[test]
+    fn passphrase_ratings_step_at_24_and_48_bits() {
+        let key = generate_key_matrix(FAIR_KEYWORD);
+        let rating = |chars: usize| analyze_passphrase(&"p".repeat(chars), &key).rating();
+        assert_eq!(rating(11), Rating::Weak);
+        assert_eq!(rating(12), Rating::Fair);
+        assert_eq!(rating(23), Rating::Fair);
+        assert_eq!(rating(24), Rating::Strong);
+        assert_eq!(
+            analyze_passphrase(&"p".repeat(100), &key).entropy_bits,
+            max_entropy_bits()
+        );
+    }
+
+    #[test]
+    fn shifts_of_the_default_matrix_are_weak_at_any_entropy() {
+        let alphabet: Vec<char> = constants::ALPHABET.chars().collect();
+        let plain = analyze_key(&keygen::export_key(&alphabet).unwrap());
+        assert_eq!(plain.resemblance, Some((Resemblance::Identity, 0)));
+        assert_eq!(plain.rating(), Rating::Weak);
+
+        let near = analyze_key("B");
+        assert_eq!(near.resemblance, Some((Resemblance::Identity, 2)));
+        assert_eq!(near.rating(), Rating::Weak);
+
+        let rotated: Vec<char> = (0..25).map(|i| alphabet[(i + 3) % 25]).collect();
+        let rotated = analyze_key(&keygen::export_key(&rotated).unwrap());
+        assert_eq!(rotated.resemblance, Some((Resemblance::Rotation(3), 0)));
+        assert_eq!(rotated.rating(), Rating::Weak);
+
+        let shifted: Vec<char> = (0..25)
+            .map(|i| alphabet[(i / 5 + 1) % 5 * 5 + (i % 5 + 2) % 5])
+            .collect();
+        let grid = analyze_passphrase(&"p".repeat(40), &shifted);
+        assert_eq!(
+            grid.resemblance,
+            Some((
+                Resemblance::GridShift {
+                    rows: 1,
+                    columns: 2
+                },
+                0
+            ))
+        );
+        assert_eq!(grid.rating(), Rating::Weak);
+    }
+}
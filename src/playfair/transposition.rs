@@ -0,0 +1,66 @@
+// Keyed columnar transposition: the input is written row by row under the
+// keyword and read off column by column in the keyword's alphabetical order
+// (ties keep their position). Keywords shorter than two letters are a no-op.
+
+fn column_order(keyword: &str) -> Vec<usize> {
+    let letters: Vec<char> = keyword
+        .to_uppercase()
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .collect();
+    let mut order: Vec<usize> = (0..letters.len()).collect();
+    order.sort_by_key(|&i| letters[i]);
+    order
+}
+
+pub fn transpose<T: Copy>(items: &[T], keyword: &str) -> Vec<T> {
+    let order = column_order(keyword);
+    if order.len() < 2 {
+        return items.to_vec();
+    }
+    let mut transposed: Vec<T> = Vec::with_capacity(items.len());
+    for column in order.iter() {
+        transposed.extend(items.iter().skip(*column).step_by(order.len()));
+    }
+    transposed
+}
+
+pub fn untranspose<T: Copy>(items: &[T], keyword: &str) -> Vec<T> {
+    let order = column_order(keyword);
+    let columns = order.len();
+    if columns < 2 || items.is_empty() {
+        return items.to_vec();
+    }
+    let rows = items.len().div_ceil(columns);
+    let full_columns = match items.len() % columns {
+        0 => columns,
+        r => r,
+    };
+
+    let mut untransposed: Vec<Option<T>> = vec![None; items.len()];
+    let mut read = items.iter();
+    for column in order.iter() {
+        let height = match *column < full_columns {
+            true => rows,
+            false => rows - 1,
+        };
+        for row in 0..height {
+            untransposed[row * columns + column] = read.next().copied();
+        }
+    }
+    untransposed.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_textbook_example() {
+        let plain: Vec<char> = "WEAREDISCOVEREDFLEEATONCE".chars().collect();
+        let transposed: String = transpose(&plain, "ZEBRAS").iter().collect();
+        assert_eq!(transposed, "EVLNACDTESEAROFODEECWIREE");
+        let back = untranspose(&transposed.chars().collect::<Vec<char>>(), "ZEBRAS");
+        assert_eq!(back, plain);
+    }
+}